@@ -0,0 +1,183 @@
+use std::{collections::{HashMap, VecDeque}, fmt::Debug, sync::{Arc, Mutex}, time::Duration};
+use alloy_network::ReceiptResponse;
+use alloy_primitives::Address;
+
+use crate::{Clock, SpendLimit, SpendLimitAction, SystemClock};
+
+/// What happens to a transaction whose fees would exceed the remaining [`GasBudget`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BudgetAction {
+    /// Fail with [`GasError::BudgetExhausted`](crate::GasError).
+    #[default]
+    Block,
+    /// Stop escalating, capping the fee cap to what is left of the budget.
+    Downgrade,
+}
+
+/// Fees spent by an account, oldest first, with the time they were recorded at.
+type Spends = VecDeque<(u64, u128)>;
+
+/// Limits the fees each account spends over rolling time windows, e.g. "1 ETH per hour and
+/// 5 ETH per day".
+///
+/// Clones share the recorded spends, so the same budget can be handed to several fillers.
+/// Spends are recorded from receipts. Until then, the fees a transaction in flight commits to
+/// are reserved, so concurrent transactions cannot each spend the whole budget.
+#[derive(Clone, Debug, Default)]
+pub struct GasBudget<C = SystemClock> {
+    windows: Vec<(u64, u128)>, // Window length in seconds and the fees allowed within it, in wei
+    action: BudgetAction,
+    spends: Arc<Mutex<HashMap<Address, Spends>>>,
+    reserved: Arc<Mutex<HashMap<(Address, u64), u128>>>, // Fees committed to by transactions in flight
+    clock: C,
+}
+
+impl GasBudget {
+    pub fn new(action: BudgetAction) -> Self {
+        Self::with_clock(action, SystemClock)
+    }
+}
+
+impl<C: Clock> GasBudget<C> {
+    pub fn with_clock(action: BudgetAction, clock: C) -> Self {
+        Self { windows: Vec::new(), action, spends: Default::default(), reserved: Default::default(), clock }
+    }
+
+    /// Allows each account at most `max_spend` wei in fees within any `window`.
+    pub fn with_window(mut self, window: Duration, max_spend: u128) -> Self {
+        self.windows.push((window.as_secs(), max_spend));
+        self
+    }
+
+    pub fn action(&self) -> BudgetAction {
+        self.action
+    }
+
+    /// Records `fee` wei spent by `from` now.
+    pub fn record_spend(&self, from: Address, fee: u128) {
+        let now = self.clock.now();
+        let longest_window = self.windows.iter().map(|(window, _)| *window).max().unwrap_or_default();
+
+        let mut spends = self.spends.lock().unwrap();
+        let account = spends.entry(from).or_default();
+        account.push_back((now, fee));
+        // spends older than every window no longer count
+        while account.front().is_some_and(|(time, _)| time + longest_window <= now) {
+            account.pop_front();
+        }
+    }
+
+    /// Records the fees paid by the transaction of `receipt`, blob fees included, and releases
+    /// what was reserved for it under `nonce`.
+    pub fn record_receipt<R: ReceiptResponse>(&self, nonce: u64, receipt: &R) {
+        self.record_spend(receipt.from(), receipt_fee(receipt));
+        self.release(receipt.from(), nonce);
+    }
+
+    /// Reserves `fee` wei for the transaction `from` has in flight with `nonce`, replacing
+    /// what was reserved for a previous version of it.
+    pub fn reserve(&self, from: Address, nonce: u64, fee: u128) {
+        self.reserved.lock().unwrap().insert((from, nonce), fee);
+    }
+
+    /// Releases the fees reserved for the transaction sent by `from` with `nonce`, once it is
+    /// mined or dropped.
+    pub fn release(&self, from: Address, nonce: u64) {
+        self.reserved.lock().unwrap().remove(&(from, nonce));
+    }
+
+    /// Releases the fees reserved for every transaction of `from` below `mined_nonce`.
+    fn release_mined(&self, from: Address, mined_nonce: u64) {
+        self.reserved.lock().unwrap().retain(|(sender, nonce), _| *sender != from || *nonce >= mined_nonce);
+    }
+
+    /// Returns the fees reserved for the transactions `from` has in flight, except `nonce`.
+    fn reserved(&self, from: Address, nonce: Option<u64>) -> u128 {
+        let reserved = self.reserved.lock().unwrap();
+        reserved
+            .iter()
+            .filter(|((sender, reserved_nonce), _)| *sender == from && Some(*reserved_nonce) != nonce)
+            .map(|(_, fee)| fee)
+            .sum()
+    }
+
+    /// Returns the fees `from` has spent within the last `window` seconds.
+    fn spent(&self, from: Address, window: u64) -> u128 {
+        let now = self.clock.now();
+        let spends = self.spends.lock().unwrap();
+        spends.get(&from).map_or(0, |account| {
+            account.iter().filter(|(time, _)| time + window > now).map(|(_, fee)| fee).sum()
+        })
+    }
+
+    /// Returns the fees `from` may still spend before exhausting any of the windows, net of
+    /// the fees reserved for its transactions in flight.
+    pub fn remaining(&self, from: Address) -> u128 {
+        self.available(from, None)
+    }
+
+    /// Returns the fees left to `from`, net of the reservations of every transaction but
+    /// `nonce`.
+    fn available(&self, from: Address, nonce: Option<u64>) -> u128 {
+        let reserved = self.reserved(from, nonce);
+        self.windows
+            .iter()
+            .map(|(window, max_spend)| max_spend.saturating_sub(self.spent(from, *window)).saturating_sub(reserved))
+            .min()
+            .unwrap_or(u128::MAX)
+    }
+}
+
+/// [`GasBudget`] with its [`Clock`] erased, as held by
+/// [`GasEscalatorFiller`](crate::GasEscalatorFiller) and
+/// [`EscalationHandle`](crate::EscalationHandle).
+pub(crate) trait Budget: Debug + Send + Sync {
+    /// Returns what is left of the budget of `from` as a limit on its transaction with
+    /// `nonce`, whose own reservation is about to be replaced.
+    fn spend_limit(&self, from: Address, nonce: u64) -> SpendLimit;
+
+    fn reserve(&self, from: Address, nonce: u64, fee: u128);
+
+    fn release(&self, from: Address, nonce: u64);
+
+    fn release_mined(&self, from: Address, mined_nonce: u64);
+
+    fn record_spend(&self, from: Address, fee: u128);
+}
+
+impl<C: Clock + 'static> Budget for GasBudget<C> {
+    fn spend_limit(&self, from: Address, nonce: u64) -> SpendLimit {
+        let action = match self.action {
+            BudgetAction::Block => SpendLimitAction::Refuse,
+            BudgetAction::Downgrade => SpendLimitAction::Clamp,
+        };
+        SpendLimit::new(self.available(from, Some(nonce)), action)
+    }
+
+    fn reserve(&self, from: Address, nonce: u64, fee: u128) {
+        GasBudget::reserve(self, from, nonce, fee);
+    }
+
+    fn release(&self, from: Address, nonce: u64) {
+        GasBudget::release(self, from, nonce);
+    }
+
+    fn release_mined(&self, from: Address, mined_nonce: u64) {
+        GasBudget::release_mined(self, from, mined_nonce);
+    }
+
+    fn record_spend(&self, from: Address, fee: u128) {
+        GasBudget::record_spend(self, from, fee);
+    }
+}
+
+/// Returns the fees paid by the transaction of `receipt`, blob fees included.
+pub(crate) fn receipt_fee<R: ReceiptResponse>(receipt: &R) -> u128 {
+    let execution_fee = receipt.gas_used().saturating_mul(receipt.effective_gas_price());
+    let blob_fee = receipt
+        .blob_gas_used()
+        .zip(receipt.blob_gas_price())
+        .map_or(0, |(blob_gas_used, blob_gas_price)| blob_gas_used.saturating_mul(blob_gas_price));
+
+    execution_fee.saturating_add(blob_fee)
+}
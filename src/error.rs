@@ -0,0 +1,50 @@
+use alloy_primitives::{Address, B256};
+use alloy_transport::{RpcError, TransportError};
+
+/// Errors raised by [`GasEscalatorFiller`](crate::GasEscalatorFiller) and its companions.
+///
+/// They surface through the [`TxFiller`](alloy_provider::fillers::TxFiller) interface as
+/// [`RpcError::LocalUsageError`]; use [`GasError::from_transport_error`] to match on them.
+#[derive(Debug, thiserror::Error)]
+pub enum GasError {
+    #[error("TransactionRequest missing 'from' field")]
+    MissingFrom,
+    #[error("TransactionRequest missing 'nonce' field")]
+    MissingNonce,
+    #[error("escalation of transaction {from}/{nonce} expired")]
+    EscalationExpired { from: Address, nonce: u64 },
+    #[error("replacing transaction {from}/{nonce} requires a bid of {required} wei, above the max bid of {max_bid} wei")]
+    MaxBidReached { from: Address, nonce: u64, required: u128, max_bid: u128 },
+    #[error("node does not support the txpool_ namespace")]
+    TxPoolUnsupported,
+    #[error("replacing transaction {from}/{nonce} requires a fee of {required} wei, above the cap of {cap} wei")]
+    ReplacementUnderpriced { from: Address, nonce: u64, required: u128, cap: u128 },
+    #[error("transaction {from}/{nonce} would commit {required} wei in fees, above the limit of {limit} wei")]
+    SpendLimitExceeded { from: Address, nonce: u64, required: u128, limit: u128 },
+    #[error("transaction {from}/{nonce} would commit {required} wei in fees, above the {remaining} wei left in the gas budget")]
+    BudgetExhausted { from: Address, nonce: u64, required: u128, remaining: u128 },
+    #[error("transaction {0} not found")]
+    TransactionNotFound(B256),
+    #[error("nonce {nonce} of {from} was consumed by a transaction that is not being escalated")]
+    NonceConsumed { from: Address, nonce: u64 },
+    #[error("transaction {from}/{nonce} was cancelled")]
+    TransactionCancelled { from: Address, nonce: u64 },
+    #[error("rebroadcast task stopped")]
+    RebroadcastStopped,
+}
+
+impl GasError {
+    /// Returns the [`GasError`] carried by `error`, if any.
+    pub fn from_transport_error(error: &TransportError) -> Option<&Self> {
+        match error {
+            RpcError::LocalUsageError(e) => e.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl From<GasError> for TransportError {
+    fn from(error: GasError) -> Self {
+        RpcError::LocalUsageError(Box::new(error))
+    }
+}
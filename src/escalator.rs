@@ -0,0 +1,276 @@
+use std::{fmt::Debug, time::{SystemTime, UNIX_EPOCH}};
+use derive_new::new;
+
+use crate::UrgencyPreset;
+
+/// Block time assumed by [`TimeEscalator`] to convert block counts, in seconds.
+const DEFAULT_BLOCK_TIME: u64 = 12;
+
+/// A bidding curve used by [`GasEscalatorFiller`](crate::GasEscalatorFiller) to price
+/// the priority fee of a transaction that is stuck in the mempool.
+pub trait Escalator: Clone + Debug + Send + Sync {
+    /// Returns the priority fee bid for block `block`, or `0` once the escalation has expired.
+    fn bid(&self, block: u64) -> u128;
+
+    /// Returns `true` if the escalation is no longer valid at block `block`.
+    fn is_expired(&self, block: u64) -> bool;
+
+    /// Returns the number of blocks left before the escalation expires, from block `block`.
+    fn remaining_blocks(&self, block: u64) -> u64;
+
+    /// Returns the maximum priority fee this escalator will ever bid.
+    fn max_bid(&self) -> u128;
+
+    /// Restarts the escalation from block `start_block`.
+    fn reset(&mut self, start_block: u64);
+
+    /// Replaces the first bid of the escalation, e.g. with a market estimate.
+    ///
+    /// Does nothing by default, so the escalation keeps its configured start bid.
+    fn set_start_bid(&mut self, _start_bid: u128) {}
+
+    /// Reconfigures the escalation from an urgency preset, starting at `start_bid` and
+    /// capped at `max_bid`.
+    ///
+    /// Does nothing by default, so urgency presets leave the escalation as configured.
+    fn apply_urgency(&mut self, _preset: &UrgencyPreset, _start_bid: u128, _max_bid: u128) {}
+}
+
+/// What [`GasEscalatorFiller`](crate::GasEscalatorFiller) does when a transaction is still
+/// pending once its escalator has expired.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpiryPolicy {
+    /// Refuse to resend, failing with [`GasError::EscalationExpired`](crate::GasError).
+    #[default]
+    Error,
+    /// Replace the transaction with a 0-value self-transfer at the same nonce, bidding `max_bid`.
+    ///
+    /// Blob transactions can only be replaced by blob transactions, so they fail instead.
+    Cancel,
+    /// Keep resending at the escalator's `max_bid`.
+    MaxBid,
+}
+
+#[derive(Clone, Debug, new)]
+pub struct LinearEscalator {
+    start_bid: u128,             // Starting bid in wei
+    increment: u128,             // Increment per block in wei
+    max_bid: u128,               // Maximum bid in wei
+    start_block: u64,           // Block number to start escalation
+    valid_length: u64,          // Duration in blocks
+}
+
+impl Default for LinearEscalator {
+    /// 1 gwei escalating by 0.1 gwei per block, up to 50 gwei over 25 blocks (~5 minutes).
+    fn default() -> Self {
+        Self::new(1_000_000_000, 100_000_000, 50_000_000_000, 0, 25)
+    }
+}
+
+impl Escalator for LinearEscalator {
+    fn bid(&self, block: u64) -> u128 {
+        if self.is_expired(block) {
+            // Transaction has expired
+            return 0;
+        }
+
+        let blocks_passed = block.saturating_sub(self.start_block);
+        let increment = (blocks_passed as u128) * self.increment;
+        std::cmp::min(self.start_bid + increment, self.max_bid)
+    }
+
+    fn is_expired(&self, block: u64) -> bool {
+        block >= self.start_block + self.valid_length
+    }
+
+    fn remaining_blocks(&self, block: u64) -> u64 {
+        (self.start_block + self.valid_length).saturating_sub(block)
+    }
+
+    fn max_bid(&self) -> u128 {
+        self.max_bid
+    }
+
+    fn reset(&mut self, start_block: u64) {
+        self.start_block = start_block;
+    }
+    fn set_start_bid(&mut self, start_bid: u128) {
+        self.start_bid = start_bid;
+    }
+
+    fn apply_urgency(&mut self, preset: &UrgencyPreset, start_bid: u128, max_bid: u128) {
+        self.start_bid = start_bid;
+        self.increment = preset.increment(start_bid);
+        self.max_bid = max_bid;
+        self.valid_length = preset.valid_length;
+    }
+}
+
+/// Escalates the bid by a fixed percentage of the previous bid every block.
+#[derive(Clone, Debug, Default, new)]
+pub struct GeometricEscalator {
+    start_bid: u128,             // Starting bid in wei
+    bump_bps: u128,              // Increase per block in basis points (1250 = +12.5%)
+    max_bid: u128,               // Maximum bid in wei
+    start_block: u64,           // Block number to start escalation
+    valid_length: u64,          // Duration in blocks
+}
+
+impl Escalator for GeometricEscalator {
+    fn bid(&self, block: u64) -> u128 {
+        if self.is_expired(block) {
+            // Transaction has expired
+            return 0;
+        }
+
+        let blocks_passed = block.saturating_sub(self.start_block);
+        let mut bid = self.start_bid;
+        for _ in 0..blocks_passed {
+            if bid >= self.max_bid {
+                break;
+            }
+            bid = bid.saturating_mul(10_000 + self.bump_bps) / 10_000;
+        }
+        std::cmp::min(bid, self.max_bid)
+    }
+
+    fn is_expired(&self, block: u64) -> bool {
+        block >= self.start_block + self.valid_length
+    }
+
+    fn remaining_blocks(&self, block: u64) -> u64 {
+        (self.start_block + self.valid_length).saturating_sub(block)
+    }
+
+    fn max_bid(&self) -> u128 {
+        self.max_bid
+    }
+
+    fn reset(&mut self, start_block: u64) {
+        self.start_block = start_block;
+    }
+    fn set_start_bid(&mut self, start_bid: u128) {
+        self.start_bid = start_bid;
+    }
+
+    fn apply_urgency(&mut self, preset: &UrgencyPreset, start_bid: u128, max_bid: u128) {
+        self.start_bid = start_bid;
+        self.bump_bps = preset.bump_percent * 100;
+        self.max_bid = max_bid;
+        self.valid_length = preset.valid_length;
+    }
+}
+
+/// A source of wall-clock time, in seconds.
+pub trait Clock: Clone + Debug + Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// [`Clock`] backed by the system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+}
+
+/// Escalates the bid linearly with the seconds elapsed since the first broadcast, so the
+/// same policy behaves identically regardless of the chain's block time.
+///
+/// The block number passed to the [`Escalator`] methods is ignored; progress is read from the
+/// [`Clock`] and [`Escalator::reset`] restarts the escalation from the current time.
+#[derive(Clone, Debug)]
+pub struct TimeEscalator<C = SystemClock> {
+    start_bid: u128,             // Starting bid in wei
+    increment: u128,             // Increment per second in wei
+    max_bid: u128,               // Maximum bid in wei
+    start_time: u64,            // Unix timestamp to start escalation
+    valid_length: u64,          // Duration in seconds
+    block_time: u64,            // Seconds per block of the chain
+    clock: C,
+}
+
+impl<C: Default> Default for TimeEscalator<C> {
+    fn default() -> Self {
+        Self {
+            start_bid: 0,
+            increment: 0,
+            max_bid: 0,
+            start_time: 0,
+            valid_length: 0,
+            block_time: DEFAULT_BLOCK_TIME,
+            clock: C::default(),
+        }
+    }
+}
+
+impl TimeEscalator {
+    pub fn new(start_bid: u128, increment: u128, max_bid: u128, valid_length: u64) -> Self {
+        Self::with_clock(start_bid, increment, max_bid, valid_length, SystemClock)
+    }
+}
+
+impl<C: Clock> TimeEscalator<C> {
+    pub fn with_clock(start_bid: u128, increment: u128, max_bid: u128, valid_length: u64, clock: C) -> Self {
+        Self {
+            start_bid,
+            increment,
+            max_bid,
+            start_time: clock.now(),
+            valid_length,
+            block_time: DEFAULT_BLOCK_TIME,
+            clock,
+        }
+    }
+
+    /// Sets the block time of the chain, used to convert between seconds and blocks, e.g. for
+    /// the valid length of urgency presets. Defaults to 12 seconds.
+    pub fn with_block_time(mut self, seconds: u64) -> Self {
+        self.block_time = std::cmp::max(seconds, 1);
+        self
+    }
+
+    fn elapsed(&self) -> u64 {
+        self.clock.now().saturating_sub(self.start_time)
+    }
+}
+
+impl<C: Clock> Escalator for TimeEscalator<C> {
+    fn bid(&self, block: u64) -> u128 {
+        if self.is_expired(block) {
+            // Transaction has expired
+            return 0;
+        }
+
+        let increment = (self.elapsed() as u128) * self.increment;
+        std::cmp::min(self.start_bid + increment, self.max_bid)
+    }
+
+    fn is_expired(&self, _block: u64) -> bool {
+        self.elapsed() >= self.valid_length
+    }
+
+    fn remaining_blocks(&self, _block: u64) -> u64 {
+        self.valid_length.saturating_sub(self.elapsed()).div_ceil(self.block_time)
+    }
+
+    fn max_bid(&self) -> u128 {
+        self.max_bid
+    }
+
+    fn reset(&mut self, _start_block: u64) {
+        self.start_time = self.clock.now();
+    }
+    fn set_start_bid(&mut self, start_bid: u128) {
+        self.start_bid = start_bid;
+    }
+
+    fn apply_urgency(&mut self, preset: &UrgencyPreset, start_bid: u128, max_bid: u128) {
+        self.start_bid = start_bid;
+        self.increment = preset.increment(start_bid) / self.block_time as u128;
+        self.max_bid = max_bid;
+        self.valid_length = preset.valid_length * self.block_time;
+    }
+}
@@ -0,0 +1,174 @@
+use alloy_consensus::BlockHeader;
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_network::{BlockResponse, Network};
+use alloy_provider::Provider;
+use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind};
+use alloy_transport::{RpcError, Transport, TransportResult};
+use alloy_primitives::Address;
+use derive_new::new;
+
+use crate::txpool::pending_tips;
+
+/// Market fees estimated by [`FeeHistoryEstimator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub base_fee: u128,             // Base fee of the next block
+    pub priority_fee: Option<u128>, // None if no recent block paid any tip
+}
+
+/// Estimates a market priority fee from the rewards reported by `eth_feeHistory`.
+///
+/// Each of the last `block_count` blocks contributes the tip paid at `percentile` of its gas;
+/// the estimate is the median of these tips, ignoring empty blocks.
+#[derive(Clone, Debug, new)]
+pub struct FeeHistoryEstimator {
+    block_count: u64,   // Number of recent blocks to sample
+    percentile: f64,    // Reward percentile within each block, from 0 to 100
+}
+
+impl Default for FeeHistoryEstimator {
+    fn default() -> Self {
+        Self::new(10, 20.0)
+    }
+}
+
+impl FeeHistoryEstimator {
+    pub fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    pub fn percentile(&self) -> f64 {
+        self.percentile
+    }
+
+    /// Returns a copy of the estimator sampling tips at `percentile` instead.
+    pub fn with_percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile;
+        self
+    }
+
+    /// Returns the next block's base fee and the estimated priority fee.
+    pub async fn estimate<P, T, N>(&self, provider: &P) -> TransportResult<FeeEstimate>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let fee_history = provider
+            .get_fee_history(self.block_count, BlockNumberOrTag::Latest, &[self.percentile])
+            .await?;
+
+        Ok(FeeEstimate {
+            base_fee: fee_history.next_block_base_fee().unwrap_or_default(),
+            priority_fee: Self::tip_from_rewards(fee_history.reward.as_deref().unwrap_or_default()),
+        })
+    }
+
+    /// Returns the median of the first reward of every block, skipping blocks without tips.
+    pub fn tip_from_rewards(rewards: &[Vec<u128>]) -> Option<u128> {
+        let mut tips: Vec<u128> = rewards
+            .iter()
+            .filter_map(|block_rewards| block_rewards.first().copied())
+            .filter(|tip| *tip > 0)
+            .collect();
+        if tips.is_empty() {
+            return None;
+        }
+
+        tips.sort_unstable();
+        Some(tips[tips.len() / 2])
+    }
+}
+
+/// Estimates the tip needed to outbid the mempool, from the effective tips of the executable
+/// transactions returned by `txpool_content`.
+///
+/// Block builders fill blocks by descending tip, so a transaction lands within the first
+/// `target_gas` of the next block if it outbids the transaction at that gas depth.
+#[derive(Clone, Debug, new)]
+pub struct MempoolTipEstimator {
+    target_gas: u64, // Gas depth of the next block to land within
+}
+
+impl Default for MempoolTipEstimator {
+    fn default() -> Self {
+        // the gas target of an Ethereum block
+        Self::new(15_000_000)
+    }
+}
+
+impl MempoolTipEstimator {
+    pub fn target_gas(&self) -> u64 {
+        self.target_gas
+    }
+
+    /// Returns the tip landing within the first `target_gas` of the next block at base fee
+    /// `base_fee`, ignoring the transactions of `from`, or `None` if the mempool does not fill it.
+    pub async fn estimate<P, T, N>(
+        &self,
+        provider: &P,
+        base_fee: u128,
+        from: Option<Address>,
+    ) -> TransportResult<Option<u128>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let tips = pending_tips(provider, base_fee, from).await?;
+        Ok(Self::tip_for_gas(tips, self.target_gas))
+    }
+
+    /// Returns the lowest tip outbidding every `(tip, gas_limit)` past the first `target_gas`
+    /// gas of `tips`, ordered by descending tip.
+    pub fn tip_for_gas(mut tips: Vec<(u128, u64)>, target_gas: u64) -> Option<u128> {
+        tips.sort_unstable_by_key(|(tip, _)| std::cmp::Reverse(*tip));
+
+        let mut gas = 0u64;
+        for (tip, gas_limit) in tips {
+            gas = gas.saturating_add(gas_limit);
+            if gas >= target_gas {
+                // displace this transaction from the target gas
+                return Some(tip + 1);
+            }
+        }
+        None
+    }
+}
+
+/// Returns the base fee of the block after the latest one, per the EIP-1559 update rule.
+pub(crate) async fn next_base_fee<P, T, N>(provider: &P, params: BaseFeeParams) -> TransportResult<u128>
+where
+    P: Provider<T, N>,
+    T: Transport + Clone,
+    N: Network,
+{
+    let latest_block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest, BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or(RpcError::NullResp)?;
+
+    latest_block
+        .header()
+        .as_ref()
+        .next_block_base_fee(params)
+        .map(u128::from)
+        .ok_or(RpcError::UnsupportedFeature("eip1559"))
+}
+
+/// Returns the highest base fee reachable `blocks` blocks after a block with base fee
+/// `base_fee`, if every block in between is full.
+pub fn max_base_fee_after(base_fee: u128, blocks: u64, params: BaseFeeParams) -> u128 {
+    let mut base_fee = base_fee;
+    for _ in 0..blocks {
+        if base_fee == u128::MAX {
+            break;
+        }
+        // a full block raises the base fee by (elasticity_multiplier - 1) / max_change_denominator,
+        // and by at least 1 wei
+        let growth = params.elasticity_multiplier.saturating_sub(1);
+        let delta = std::cmp::max(base_fee.saturating_mul(growth) / params.max_change_denominator, 1);
+        base_fee = base_fee.saturating_add(delta);
+    }
+    base_fee
+}
@@ -1,12 +1,12 @@
-use core::time;
 use std::sync::{Arc, Mutex};
-use alloy_consensus::BlockHeader;
 
-use alloy_network::{BlockResponse, Network, TransactionBuilder};
+use alloy_network::Network;
 use alloy_primitives::{B256, U256};
-use alloy_provider::{ext::{AnvilApi, TxPoolApi}, Provider, ProviderBuilder, WalletProvider};
-use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind, TransactionRequest, TransactionTrait};
-use alloy_transport::{RpcError, Transport, TransportResult};
+use alloy_provider::{ext::AnvilApi, Provider, WalletProvider};
+use alloy_rpc_types::{TransactionRequest, TransactionTrait};
+use alloy_transport::{Transport, TransportResult};
+
+use crate::GasError;
 
 #[derive(Clone)]
 pub struct Gas1559Config {
@@ -49,24 +49,21 @@ impl GasAnvil {
         };
 
         if let Some(cfg) = config {
-            let tx = provider.get_transaction_by_hash(tx_hash).await.unwrap();
-            let tx = tx.ok_or(RpcError::LocalUsageError(Box::new(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Transaction not found",
-            ))))?;
+            let tx = provider.get_transaction_by_hash(tx_hash).await?;
+            let tx = tx.ok_or(GasError::TransactionNotFound(tx_hash))?;
             
             if tx.max_fee_per_gas() >= cfg.max_fee_per_gas && 
                 tx.max_priority_fee_per_gas().unwrap_or(0) >= cfg.max_priority_fee_per_gas {
                 // Mine the block including this transaction
                 // provider.evm_mine(None).await?;
-                provider.anvil_mine(Some(U256::from(1)), None).await.unwrap();
+                provider.anvil_mine(Some(U256::from(1)), None).await?;
                 println!("Mined transaction {:?}", tx_hash);
             } else {
-                provider.anvil_drop_transaction(tx_hash).await.unwrap();
-                provider.anvil_mine(None, None).await.unwrap();
+                provider.anvil_drop_transaction(tx_hash).await?;
+                provider.anvil_mine(None, None).await?;
 
                 // provider.
-                let _tx = provider.eth_send_unsigned_transaction(transaction_request.into()).await.unwrap();
+                let _tx = provider.eth_send_unsigned_transaction(transaction_request.into()).await?;
 
                 println!("Transaction {:?} does not meet the gas requirements.", tx_hash);
             }
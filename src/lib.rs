@@ -1,107 +1,793 @@
 use std::{collections::HashMap, future::IntoFuture, sync::{Arc, Mutex}};
-use alloy_primitives::B256;
-use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind, TransactionTrait};
+use alloy_primitives::{Address, Bytes, B256, U256};
 use alloy_consensus::BlockHeader;
-use alloy_network::{BlockResponse, Network, TransactionBuilder, TransactionResponse};
-use alloy_provider::{ext::TxPoolApi,  fillers::{FillerControlFlow, GasFillable, TxFiller}, utils::Eip1559Estimation, Provider, SendableTx};
+use alloy_eips::{eip1559::BaseFeeParams, eip4844::BLOB_TX_MIN_BLOB_GASPRICE};
+use alloy_network::{BlockResponse, Network, ReceiptResponse, TransactionBuilder, TransactionBuilder4844};
+use alloy_provider::{fillers::{FillerControlFlow, GasFillable, TxFiller}, utils::Eip1559Estimation, Provider, SendableTx};
+use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind};
 use alloy_transport::{RpcError, Transport, TransportResult};
 use futures::FutureExt;
-use derive_new::new; 
 
+mod budget;
+mod error;
+mod escalator;
+mod estimator;
+#[cfg(test)]
 mod gas_anvil;
+#[cfg(test)]
+mod mock_node;
+mod oracle;
+mod rebroadcast;
+mod replacement;
+/// Offline replay of historical blocks, to tune escalation policies without sending anything.
+pub mod simulation;
+mod spend_limit;
+mod txpool;
+mod urgency;
+
+pub use budget::{BudgetAction, GasBudget};
+pub use error::GasError;
+pub use escalator::{Clock, Escalator, ExpiryPolicy, GeometricEscalator, LinearEscalator, SystemClock, TimeEscalator};
+pub use estimator::{max_base_fee_after, FeeEstimate, FeeHistoryEstimator, MempoolTipEstimator};
+pub use oracle::{AggregateOracle, Aggregation, FeeHistoryOracle, GasOracle, MempoolOracle, StaticOracle};
+pub use rebroadcast::{EscalationHandle, EscalatorProviderExt};
+pub use replacement::ReplacementPolicy;
+pub use spend_limit::{SpendLimit, SpendLimitAction};
+pub use txpool::{PendingDetection, PooledTransaction, SubPool};
+pub use urgency::{Urgency, UrgencyPreset};
+
+use estimator::next_base_fee;
+use budget::{receipt_fee, Budget};
+use txpool::TxPoolLookup;
+
+/// Escalation progress of a single in-flight transaction, keyed by `(from, nonce)`.
+#[derive(Clone, Debug)]
+pub struct EscalationState<E> {
+    pub escalator: E,           // Escalator restarted at `start_block`
+    pub start_block: u64,       // Block of the first broadcast
+    pub current_bid: u128,      // Last bid handed out for this transaction
+    pub attempts: u64,          // Number of times the transaction was (re)sent
+    pub tx_hash: Option<B256>,  // Last known hash, used when the txpool cannot be inspected
+    pub blob_fee: Option<u128>, // Last max_fee_per_blob_gas handed out, for blob transactions
+    pub max_fee_per_gas: u128,  // Fee cap (or gas price) of the last broadcast
+    pub max_priority_fee_per_gas: u128, // Tip (or gas price) of the last broadcast
+    pub cancelled: bool,        // Resends are replaced by a 0-value self-transfer
+}
 
-
-#[derive(Clone, Debug, Default, new)]
-pub struct LinearEscalator {
-    start_bid: u128,             // Starting bid in wei
-    increment: u128,             // Increment per block in wei
-    max_bid: u128,               // Maximum bid in wei
-    start_block: u64,           // Block number to start escalation
-    valid_length: u64,          // Duration in blocks
-    current_bid: Arc<Mutex<u128>>, // Tracks current bid per transaction 
+/// Gas fields prepared by [`GasEscalatorFiller`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscalatorFillable {
+    pub gas: GasFillable,
+    pub max_fee_per_blob_gas: Option<u128>, // Only set for EIP-4844 transactions
+    pub cancel: bool,                       // Rewrite the transaction into a cancellation
 }
 
-impl LinearEscalator {
-    pub fn update_bid(&self, current_block: u64) -> u128 {
-        let mut current_bid = self.current_bid.lock().unwrap();
+/// Gas used by a plain ether transfer, and so by a cancellation.
+const CANCEL_GAS_LIMIT: u64 = 21_000;
 
-        if current_block >= self.start_block + self.valid_length {
-            // Transaction has expired
-            *current_bid = 0;
-        } else {
-            let blocks_passed = current_block.saturating_sub(self.start_block);
-            let increment = (blocks_passed as u128) * self.increment;
-            *current_bid = std::cmp::min(self.start_bid + increment, self.max_bid);
-        }
+/// Blocks of base fee growth covered by `max_fee_per_gas` by default, keeping it within ~2x of
+/// the next base fee on Ethereum.
+const DEFAULT_MAX_HEADROOM_BLOCKS: u64 = 6;
 
-        *current_bid
-    }
+/// Fee multiple geth and reth require to replace a blob transaction, on every fee field.
+const BLOB_REPLACEMENT_MULTIPLE: u128 = 2;
 
-    pub fn current_bid(&self) -> u128 {
-        *self.current_bid.lock().unwrap()
-    }
+
+type EscalationStates<E> = Arc<Mutex<HashMap<(Address, u64), EscalationState<E>>>>;
+
+/// Settings of a single transaction, overriding the filler's.
+#[derive(Clone, Copy, Debug, Default)]
+struct TxOverrides {
+    urgency: Option<Urgency>,
+    spend_limit: Option<SpendLimit>,
+    cancel_requested: bool, // A cancellation was sent, so resends of the original are refused
+}
+
+/// The `(from, nonce)` of the transaction being filled and what was already sent for it.
+struct PendingContext {
+    from: Address,
+    nonce: u64,
+    chain_id: Option<u64>,
+    current_block: u64,
+    mined_nonce: u64,
+    pending_tx: Option<PooledTransaction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GasEscalatorFiller<E = LinearEscalator> {
+    escalator: E,
+    states: EscalationStates<E>,
+    lookup: TxPoolLookup,
+    max_blob_fee: u128,
+    replacement: ReplacementPolicy,
+    expiry: ExpiryPolicy,
+    estimator: Option<FeeHistoryEstimator>, // Seeds the first bid of every escalation
+    oracle: Option<Arc<dyn GasOracle>>,     // Replaces the estimator when set
+    mempool_estimator: Option<MempoolTipEstimator>, // Outbids the mempool on every attempt
+    urgency: Option<Urgency>,               // Preset applied to transactions without their own
+    spend_limit: Option<SpendLimit>,        // Limit applied to transactions without their own
+    overrides: Arc<Mutex<HashMap<(Address, u64), TxOverrides>>>,
+    budget: Option<Arc<dyn Budget>>,        // Fees allowed per account over rolling windows
+    base_fee_params: BaseFeeParams,
+    max_headroom_blocks: u64, // Cap on the blocks of base fee growth covered by max_fee_per_gas
 }
 
-#[derive(Clone, Debug, Default)]
-pub struct GasEscalatorFiller {
-    escalator: LinearEscalator,
+impl Default for GasEscalatorFiller {
+    fn default() -> Self {
+        Self::with_escalator(LinearEscalator::default())
+    }
 }
 
-impl GasEscalatorFiller {
-    pub fn with_escalator(escalator: LinearEscalator) -> Self {
+impl<E: Escalator> GasEscalatorFiller<E> {
+    pub fn with_escalator(escalator: E) -> Self {
         Self {
             escalator,
+            states: Default::default(),
+            lookup: Default::default(),
+            max_blob_fee: u128::MAX,
+            replacement: Default::default(),
+            expiry: Default::default(),
+            estimator: None,
+            oracle: None,
+            mempool_estimator: None,
+            urgency: None,
+            spend_limit: None,
+            overrides: Default::default(),
+            budget: None,
+            base_fee_params: BaseFeeParams::ethereum(),
+            max_headroom_blocks: DEFAULT_MAX_HEADROOM_BLOCKS,
+        }
+    }
+
+    /// Sets the EIP-1559 parameters used to predict the base fee of upcoming blocks.
+    pub fn with_base_fee_params(mut self, base_fee_params: BaseFeeParams) -> Self {
+        self.base_fee_params = base_fee_params;
+        self
+    }
+
+    /// Caps the blocks of worst-case base fee growth `max_fee_per_gas` covers, 6 by default.
+    /// Raising it keeps long escalations includable through longer fee spikes, at the cost of
+    /// fee caps the sender's balance may not cover, e.g. ~320x the base fee over 50 blocks.
+    pub fn with_max_headroom_blocks(mut self, max_headroom_blocks: u64) -> Self {
+        self.max_headroom_blocks = max_headroom_blocks;
+        self
+    }
+
+    /// Starts every escalation from a bid estimated by `oracle`, instead of the escalator's
+    /// `start_bid` or the connected node's fee history. Its base fee also replaces the node's
+    /// in `max_fee_per_gas`.
+    pub fn with_oracle(mut self, oracle: impl GasOracle + 'static) -> Self {
+        self.oracle = Some(Arc::new(oracle));
+        self
+    }
+
+    /// Prices every attempt to land within the first `target_gas` of the next block, given the
+    /// tips in the mempool. The escalator's bid still applies when it is higher, and its max bid
+    /// caps the result.
+    ///
+    /// The mempool is read through `txpool_content` once per block; on nodes without the
+    /// `txpool_` namespace the escalator's bid is used alone.
+    pub fn with_mempool_tip_estimator(mut self, estimator: MempoolTipEstimator) -> Self {
+        self.mempool_estimator = Some(estimator);
+        self
+    }
+
+    /// Escalates every transaction with the preset of `urgency`, replacing the escalator's
+    /// parameters. The first bid is taken from `eth_feeHistory` at the preset's percentile.
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = Some(urgency);
+        self
+    }
+
+    /// Escalates the transaction sent by `from` with `nonce` with the preset of `urgency`.
+    ///
+    /// Must be called before the transaction is first sent.
+    pub fn set_urgency(&self, from: Address, nonce: u64, urgency: Urgency) {
+        self.overrides.lock().unwrap().entry((from, nonce)).or_default().urgency = Some(urgency);
+    }
+
+    /// Returns the urgency the transaction sent by `from` with `nonce` is escalated with.
+    pub fn urgency(&self, from: Address, nonce: u64) -> Option<Urgency> {
+        let overrides = self.overrides.lock().unwrap();
+        overrides.get(&(from, nonce)).and_then(|overrides| overrides.urgency).or(self.urgency)
+    }
+
+    /// Caps the total fee of every transaction.
+    pub fn with_spend_limit(mut self, spend_limit: SpendLimit) -> Self {
+        self.spend_limit = Some(spend_limit);
+        self
+    }
+
+    /// Consults `budget` before every fill, so no account spends more than it allows, and
+    /// reserves the fees each fill commits to until its nonce is mined.
+    ///
+    /// Keep a clone of the budget and record receipts on it, through
+    /// [`GasEscalatorFiller::record_receipt`] or with [`EscalationHandle::with_budget`].
+    pub fn with_budget<C: Clock + 'static>(mut self, budget: GasBudget<C>) -> Self {
+        self.budget = Some(Arc::new(budget));
+        self
+    }
+
+    /// Records the fees paid by the transaction of `receipt`, sent with `nonce`, against the gas
+    /// budget, if any, and releases what was reserved for it.
+    pub fn record_receipt<R: ReceiptResponse>(&self, nonce: u64, receipt: &R) {
+        if let Some(budget) = &self.budget {
+            budget.record_spend(receipt.from(), receipt_fee(receipt));
+            budget.release(receipt.from(), nonce);
         }
     }
 
-    pub fn escalator(&self) -> &LinearEscalator {
+    /// Caps the total fee of the transaction sent by `from` with `nonce`.
+    pub fn set_spend_limit(&self, from: Address, nonce: u64, spend_limit: SpendLimit) {
+        self.overrides.lock().unwrap().entry((from, nonce)).or_default().spend_limit = Some(spend_limit);
+    }
+
+    /// Returns the limit on the total fee of the transaction sent by `from` with `nonce`.
+    pub fn spend_limit(&self, from: Address, nonce: u64) -> Option<SpendLimit> {
+        let overrides = self.overrides.lock().unwrap();
+        overrides.get(&(from, nonce)).and_then(|overrides| overrides.spend_limit).or(self.spend_limit)
+    }
+
+    /// Starts every escalation from a bid estimated from recent `eth_feeHistory` rewards,
+    /// instead of the escalator's `start_bid`.
+    pub fn with_fee_history_estimator(mut self, estimator: FeeHistoryEstimator) -> Self {
+        self.estimator = Some(estimator);
+        self
+    }
+
+    /// Sets what happens to a transaction that is still pending once its escalator expired.
+    pub fn with_expiry_policy(mut self, expiry: ExpiryPolicy) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Sets the minimum fee bump applied when replacing a transaction already in the mempool.
+    pub fn with_replacement_policy(mut self, replacement: ReplacementPolicy) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Caps the `max_fee_per_blob_gas` of blob transactions, independently of the escalator's
+    /// execution tip cap.
+    pub fn with_max_blob_fee(mut self, max_blob_fee: u128) -> Self {
+        self.max_blob_fee = max_blob_fee;
+        self
+    }
+
+    /// Sets how transactions already in the mempool are detected.
+    pub fn with_pending_detection(mut self, detection: PendingDetection) -> Self {
+        self.lookup = TxPoolLookup::new(detection);
+        self
+    }
+
+    pub fn escalator(&self) -> &E {
         &self.escalator
     }
 
-    // async fn find
-    async fn get_transaction<P, T, N>(
+    /// Returns the escalation state of the transaction sent by `from` with `nonce`, if tracked.
+    pub fn state(&self, from: Address, nonce: u64) -> Option<EscalationState<E>> {
+        self.states.lock().unwrap().get(&(from, nonce)).cloned()
+    }
+
+    /// Remembers the hash of the last broadcast of `(from, nonce)`, so it can still be reported
+    /// on nodes that do not expose the `txpool_` namespace.
+    ///
+    /// This also confirms the broadcast was accepted, so resends within the same block trust
+    /// the cached txpool instead of asking the node again.
+    pub fn record_tx_hash(&self, from: Address, nonce: u64, tx_hash: B256) {
+        if let Some(state) = self.states.lock().unwrap().get_mut(&(from, nonce)) {
+            state.tx_hash = Some(tx_hash);
+        }
+        self.lookup.confirm_sent(from, nonce, tx_hash);
+    }
+
+    /// Stops tracking the transaction sent by `from` with `nonce`.
+    pub fn clear(&self, from: Address, nonce: u64) {
+        self.states.lock().unwrap().remove(&(from, nonce));
+    }
+
+    /// Drops the state of every transaction of `from` whose nonce has already been mined.
+    fn clear_mined(&self, from: Address, mined_nonce: u64) {
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|(sender, nonce), _| *sender != from || *nonce >= mined_nonce);
+        self.overrides
+            .lock()
+            .unwrap()
+            .retain(|(sender, nonce), _| *sender != from || *nonce >= mined_nonce);
+        if let Some(budget) = &self.budget {
+            budget.release_mined(from, mined_nonce);
+        }
+    }
+
+    /// Records a (re)send of `(from, nonce)` at `current_block` and returns the bid to use.
+    ///
+    /// A first send starts a fresh escalation from `current_block`, a resend advances the
+    /// existing one.
+    fn next_bid(&self, from: Address, nonce: u64, current_block: u64, resend: bool) -> u128 {
+        let mut states = self.states.lock().unwrap();
+        let state = match states.get_mut(&(from, nonce)) {
+            Some(state) if resend => state,
+            _ => {
+                let mut escalator = self.escalator.clone();
+                escalator.reset(current_block);
+                states.insert((from, nonce), EscalationState {
+                    escalator,
+                    start_block: current_block,
+                    current_bid: 0,
+                    attempts: 0,
+                    tx_hash: None,
+                    blob_fee: None,
+                    max_fee_per_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    cancelled: false,
+                });
+                states.get_mut(&(from, nonce)).unwrap()
+            }
+        };
+
+        state.current_bid = state.escalator.bid(current_block);
+        state.attempts += 1;
+        state.current_bid
+    }
+
+    /// Starts a fresh escalation of the transaction of `ctx`, reconfigured by `configure`, and
+    /// returns the first bid.
+    fn start_escalation(&self, ctx: &PendingContext, configure: impl FnOnce(&mut E)) -> u128 {
+        self.next_bid(ctx.from, ctx.nonce, ctx.current_block, false);
+
+        let mut states = self.states.lock().unwrap();
+        let state = states.get_mut(&(ctx.from, ctx.nonce)).unwrap();
+        configure(&mut state.escalator);
+        state.current_bid = state.escalator.bid(ctx.current_block);
+        state.current_bid
+    }
+
+    /// Estimates the market fees a new escalation of the transaction of `ctx` starts from, if
+    /// an oracle, an estimator or an urgency is configured.
+    async fn market_fees<P, T, N>(
+        &self,
+        provider: &P,
+        ctx: &PendingContext,
+    ) -> TransportResult<Option<FeeEstimate>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        if let Some(oracle) = &self.oracle {
+            let estimator = self.estimator.clone().unwrap_or_default();
+            let percentile = match self.urgency(ctx.from, ctx.nonce) {
+                Some(urgency) => urgency.preset().percentile,
+                None => estimator.percentile(),
+            };
+            return Ok(Some(oracle.estimate(percentile).await?));
+        }
+
+        let estimator = match self.urgency(ctx.from, ctx.nonce) {
+            Some(urgency) => {
+                let estimator = self.estimator.clone().unwrap_or_default();
+                Some(estimator.with_percentile(urgency.preset().percentile))
+            }
+            None => self.estimator.clone(),
+        };
+
+        match estimator {
+            Some(estimator) => Ok(Some(estimator.estimate(provider).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the tip outbidding the mempool for the transaction of `ctx`, if a mempool
+    /// estimator is configured, the node exposes its mempool and the mempool is competitive.
+    async fn competitive_tip<P, T, N>(
+        &self,
+        provider: &P,
+        ctx: &PendingContext,
+        base_fee: u128,
+    ) -> TransportResult<Option<u128>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let Some(estimator) = &self.mempool_estimator else {
+            return Ok(None);
+        };
+
+        let tips = self.lookup.pending_tips(provider, ctx.current_block, base_fee, Some(ctx.from)).await?;
+        Ok(tips.and_then(|tips| MempoolTipEstimator::tip_for_gas(tips, estimator.target_gas())))
+    }
+
+    /// Raises the current bid of the transaction of `ctx` to `tip`, within its max bid, and
+    /// returns the new bid.
+    fn raise_bid(&self, ctx: &PendingContext, tip: u128) -> u128 {
+        let mut states = self.states.lock().unwrap();
+        let Some(state) = states.get_mut(&(ctx.from, ctx.nonce)) else {
+            return tip;
+        };
+
+        let bid = std::cmp::max(state.current_bid, tip);
+        state.current_bid = std::cmp::min(bid, state.escalator.max_bid());
+        state.current_bid
+    }
+
+    /// Records a resend of the transaction of `ctx` and returns the bid to use, applying the
+    /// expiry policy once its escalator has expired.
+    fn resend_bid(&self, ctx: &PendingContext) -> Result<u128, GasError> {
+        let bid = self.next_bid(ctx.from, ctx.nonce, ctx.current_block, true);
+        let cancel_requested = self.cancel_requested(ctx.from, ctx.nonce);
+
+        let mut states = self.states.lock().unwrap();
+        let Some(state) = states.get_mut(&(ctx.from, ctx.nonce)) else {
+            return Ok(bid);
+        };
+        if !state.escalator.is_expired(ctx.current_block) {
+            return Ok(bid);
+        }
+        if cancel_requested {
+            // a cancellation has to get through whatever the policy
+            return Ok(state.escalator.max_bid());
+        }
+
+        match self.expiry {
+            ExpiryPolicy::Error => Err(GasError::EscalationExpired { from: ctx.from, nonce: ctx.nonce }),
+            ExpiryPolicy::Cancel => {
+                if !state.cancelled {
+                    println!("Escalation of {}/{} expired, cancelling", ctx.from, ctx.nonce);
+                }
+                state.cancelled = true;
+                Ok(state.escalator.max_bid())
+            }
+            ExpiryPolicy::MaxBid => Ok(state.escalator.max_bid()),
+        }
+    }
+
+    /// Returns `true` if a cancellation of `(from, nonce)` was sent.
+    fn cancel_requested(&self, from: Address, nonce: u64) -> bool {
+        let overrides = self.overrides.lock().unwrap();
+        overrides.get(&(from, nonce)).is_some_and(|overrides| overrides.cancel_requested)
+    }
+
+    /// Returns `true` if resends of `(from, nonce)` are replaced by a cancellation.
+    fn is_cancelled(&self, from: Address, nonce: u64) -> bool {
+        self.state(from, nonce).is_some_and(|state| state.cancelled)
+    }
+
+    /// Looks up the transaction already sent for the `(from, nonce)` of `tx`.
+    async fn find_pending<P, T, N>(
         &self,
         provider: &P,
         tx: &N::TransactionRequest,
-    ) -> TransportResult<Option<B256>>
+    ) -> TransportResult<PendingContext>
     where
         P: Provider<T, N>,
         T: Transport + Clone,
         N: Network,
     {
-        let from = tx.from().ok_or(RpcError::LocalUsageError(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, "TransactionRequest missing 'from' field"))))?;
-        let nonce = tx.nonce().ok_or(RpcError::LocalUsageError(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, "TransactionRequest missing 'nonce' field"))))?;
+        let from = tx.from().ok_or(GasError::MissingFrom)?;
+        let nonce = tx.nonce().ok_or(GasError::MissingNonce)?;
+
+        let (current_block, mined_nonce) = futures::try_join!(
+            provider.get_block_number().into_future(),
+            provider.get_transaction_count(from).into_future(),
+        )?;
+        self.clear_mined(from, mined_nonce);
+
+        if is_cancellation(tx) {
+            self.overrides.lock().unwrap().entry((from, nonce)).or_default().cancel_requested = true;
+        } else if self.cancel_requested(from, nonce) {
+            return Err(GasError::TransactionCancelled { from, nonce }.into());
+        }
+
+        let chain_id = match tx.chain_id() {
+            None if self.replacement.has_chain_overrides() => Some(provider.get_chain_id().await?),
+            chain_id => chain_id,
+        };
+
+        let known_hash = self.state(from, nonce).and_then(|state| state.tx_hash);
+        let pending_tx = self.lookup.find(provider, from, nonce, known_hash, current_block).await?;
+
+        Ok(PendingContext { from, nonce, chain_id, current_block, mined_nonce, pending_tx })
+    }
+
+    /// Returns the lowest `(max_fee_per_gas, max_priority_fee_per_gas)` that replaces the pooled
+    /// transaction, priced from its actual fees when known, from the last broadcast otherwise
+    /// and from `fallback` as a last resort. Blob transactions must double both fees.
+    fn min_replacement_fees(&self, ctx: &PendingContext, fallback: (u128, u128), blob: bool) -> (u128, u128) {
+        let pooled = ctx.pending_tx.as_ref().and_then(|pooled| {
+            Some((pooled.max_fee_per_gas?, pooled.max_priority_fee_per_gas?))
+        });
+        let sent = || {
+            self.state(ctx.from, ctx.nonce)
+                .filter(|state| state.max_fee_per_gas > 0)
+                .map(|state| (state.max_fee_per_gas, state.max_priority_fee_per_gas))
+        };
+        let (max_fee_per_gas, max_priority_fee_per_gas) = pooled.or_else(sent).unwrap_or(fallback);
+        if blob {
+            return (
+                max_fee_per_gas.saturating_mul(BLOB_REPLACEMENT_MULTIPLE),
+                max_priority_fee_per_gas.saturating_mul(BLOB_REPLACEMENT_MULTIPLE),
+            );
+        }
+
+        (
+            self.replacement.min_replacement_fee(max_fee_per_gas, ctx.chain_id),
+            self.replacement.min_replacement_fee(max_priority_fee_per_gas, ctx.chain_id),
+        )
+    }
+
+    /// Returns the base fee `max_fee_per_gas` must cover so the transaction of `ctx` stays
+    /// includable until its escalation expires, given the next block's base fee.
+    fn max_base_fee(&self, ctx: &PendingContext, next_base_fee: u128) -> u128 {
+        let remaining_blocks = match self.state(ctx.from, ctx.nonce) {
+            Some(state) => state.escalator.remaining_blocks(ctx.current_block),
+            None => 0,
+        };
+        // the next block's base fee is known, only the blocks after it can grow
+        let blocks = std::cmp::min(remaining_blocks.saturating_sub(1), self.max_headroom_blocks);
+
+        max_base_fee_after(next_base_fee, blocks, self.base_fee_params)
+    }
+
+    /// Fails if replacing the transaction of `ctx` requires bidding `required` wei, above the
+    /// max bid of its escalator.
+    fn check_max_bid(&self, ctx: &PendingContext, required: u128) -> Result<(), GasError> {
+        let max_bid = match self.state(ctx.from, ctx.nonce) {
+            Some(state) => state.escalator.max_bid(),
+            None => self.escalator.max_bid(),
+        };
+        if required > max_bid {
+            return Err(GasError::MaxBidReached { from: ctx.from, nonce: ctx.nonce, required, max_bid });
+        }
+        Ok(())
+    }
+
+    /// Returns the escalated bid for resending the transaction of `ctx` over `pooled`, which
+    /// needs a bid of `required` wei to be replaced, or `None` if `pooled` is queued behind a
+    /// nonce gap.
+    fn escalate_pooled(
+        &self,
+        ctx: &PendingContext,
+        pooled: &PooledTransaction,
+        required: u128,
+    ) -> Result<Option<u128>, GasError> {
+        if pooled.pool == SubPool::Queued {
+            // a higher bid cannot fix a nonce gap, only satisfy the replacement rule
+            println!(
+                "Transaction {:?} ({}/{}) is queued behind a nonce gap, not escalating",
+                pooled.tx_hash, ctx.from, ctx.nonce
+            );
+            return Ok(None);
+        }
+
+        self.check_max_bid(ctx, required)?;
+        let new_bid = self.resend_bid(ctx)?;
+        println!(
+            "Retrying transaction {:?} ({}/{}) with increased bid: {} wei",
+            pooled.tx_hash, ctx.from, ctx.nonce, new_bid
+        );
 
-        let txpool_content = provider.txpool_content().await?;
+        Ok(Some(new_bid))
+    }
+
+    /// Enforces the spend limit and the gas budget of the transaction of `ctx` on `estimate`,
+    /// given its gas limit, the lowest fee cap replacing the pooled transaction and the next
+    /// block's base fee.
+    fn limit_spend(
+        &self,
+        ctx: &PendingContext,
+        gas_limit: u64,
+        estimate: Eip1559Estimation,
+        min_fee_per_gas: u128,
+        base_fee: u128,
+    ) -> Result<Eip1559Estimation, GasError> {
+        let mut estimate = estimate;
+        if let Some(limit) = self.spend_limit(ctx.from, ctx.nonce) {
+            estimate = self.apply_limit(ctx, gas_limit, estimate, (min_fee_per_gas, base_fee), limit, |required| {
+                GasError::SpendLimitExceeded { from: ctx.from, nonce: ctx.nonce, required, limit: limit.max_total_fee() }
+            })?;
+        }
+        if let Some(budget) = &self.budget {
+            let limit = budget.spend_limit(ctx.from, ctx.nonce);
+            estimate = self.apply_limit(ctx, gas_limit, estimate, (min_fee_per_gas, base_fee), limit, |required| {
+                GasError::BudgetExhausted { from: ctx.from, nonce: ctx.nonce, required, remaining: limit.max_total_fee() }
+            })?;
+            budget.reserve(ctx.from, ctx.nonce, estimate.max_fee_per_gas.saturating_mul(gas_limit as u128));
+        }
+        Ok(estimate)
+    }
+
+    /// Refuses or clamps `estimate` if it commits the transaction of `ctx` to more than
+    /// `limit`, building the refusal from the required total fee with `exceeded`. A clamp below
+    /// the replacement fee cap or the base fee of `(min_fee_per_gas, base_fee)` is refused.
+    fn apply_limit(
+        &self,
+        ctx: &PendingContext,
+        gas_limit: u64,
+        estimate: Eip1559Estimation,
+        (min_fee_per_gas, base_fee): (u128, u128),
+        limit: SpendLimit,
+        exceeded: impl FnOnce(u128) -> GasError,
+    ) -> Result<Eip1559Estimation, GasError> {
+        let max_fee_per_gas = limit.max_fee_per_gas(gas_limit);
+        if estimate.max_fee_per_gas <= max_fee_per_gas {
+            return Ok(estimate);
+        }
 
-        for (sender, txs) in txpool_content.pending {
-            if sender != from {
-                continue;
+        let required = estimate.max_fee_per_gas.saturating_mul(gas_limit as u128);
+        match limit.action() {
+            SpendLimitAction::Refuse => Err(exceeded(required)),
+            // a fee cap below the base fee would never be included
+            SpendLimitAction::Clamp if max_fee_per_gas < base_fee => Err(exceeded(required)),
+            SpendLimitAction::Clamp if max_fee_per_gas < min_fee_per_gas => Err(GasError::ReplacementUnderpriced {
+                from: ctx.from,
+                nonce: ctx.nonce,
+                required: min_fee_per_gas,
+                cap: max_fee_per_gas,
+            }),
+            SpendLimitAction::Clamp => {
+                println!(
+                    "Fees of {}/{} exceed the spend limit, clamping max fee per gas to {} wei",
+                    ctx.from, ctx.nonce, max_fee_per_gas
+                );
+                Ok(Eip1559Estimation {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas: std::cmp::min(estimate.max_priority_fee_per_gas, max_fee_per_gas),
+                })
             }
+        }
+    }
+
+    /// Records the fees about to be broadcast for the transaction of `ctx`.
+    fn record_sent(&self, ctx: &PendingContext, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) {
+        if let Some(state) = self.states.lock().unwrap().get_mut(&(ctx.from, ctx.nonce)) {
+            state.max_fee_per_gas = max_fee_per_gas;
+            state.max_priority_fee_per_gas = max_priority_fee_per_gas;
+        }
+        self.lookup.note_sent(
+            ctx.from,
+            ctx.nonce,
+            ctx.mined_nonce,
+            ctx.current_block,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        );
+    }
+
+    async fn prepare_legacy<P, T, N>(
+        &self,
+        provider: &P,
+        tx: &N::TransactionRequest,
+    ) -> TransportResult<GasFillable>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let gas_price_fut = tx.gas_price().map_or_else(
+            || provider.get_gas_price().right_future(),
+            |gas_price| async move { Ok(gas_price) }.left_future(),
+        );
 
-            for (_, pending_tx) in txs {
-                if pending_tx.nonce() == nonce {
-                    return Ok(Some(pending_tx.tx_hash()));
+        let gas_limit_fut = tx.gas_limit().map_or_else(
+            || provider.estimate_gas(tx).into_future().right_future(),
+            |gas_limit| async move { Ok(gas_limit) }.left_future(),
+        );
+
+        let (gas_price, gas_limit) = futures::try_join!(gas_price_fut, gas_limit_fut)?;
+        let ctx = self.find_pending(provider, tx).await?;
+        let (replacement_gas_price, _) = self.min_replacement_fees(&ctx, (gas_price, gas_price), false);
+
+        let gas_price = match ctx.pending_tx {
+            Some(ref pooled) => {
+                let required = replacement_gas_price.saturating_sub(gas_price);
+                match self.escalate_pooled(&ctx, pooled, required)? {
+                    Some(new_bid) => std::cmp::max(gas_price.saturating_add(new_bid), replacement_gas_price),
+                    None => std::cmp::max(gas_price, replacement_gas_price),
                 }
             }
-        }
+            None => {
+                self.next_bid(ctx.from, ctx.nonce, ctx.current_block, false);
+                gas_price
+            }
+        };
+        let min_gas_price = if ctx.pending_tx.is_some() { replacement_gas_price } else { 0 };
+        // only needed to bound a clamped gas price
+        let base_fee = if self.spend_limit(ctx.from, ctx.nonce).is_some() || self.budget.is_some() {
+            match next_base_fee(provider, self.base_fee_params).await {
+                Err(RpcError::UnsupportedFeature(_)) => 0,
+                base_fee => base_fee?,
+            }
+        } else {
+            0
+        };
+        let gas_price = self
+            .limit_spend(
+                &ctx,
+                gas_limit,
+                Eip1559Estimation { max_fee_per_gas: gas_price, max_priority_fee_per_gas: gas_price },
+                min_gas_price,
+                base_fee,
+            )?
+            .max_fee_per_gas;
+        self.record_sent(&ctx, gas_price, gas_price);
+
+        println!("🚀 Gas Price: {:?}", gas_price);
+
+        Ok(GasFillable::Legacy { gas_limit, gas_price })
+    }
+
+    /// Prices `max_fee_per_blob_gas` for a blob transaction, to be called after its execution
+    /// fees were prepared. A replacement must double the previous blob fee, and is refused with
+    /// [`GasError::ReplacementUnderpriced`] when that exceeds the blob fee cap.
+    async fn prepare_blob_fee<P, T, N>(
+        &self,
+        provider: &P,
+        tx: &N::TransactionRequest,
+    ) -> TransportResult<u128>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+        N::TransactionRequest: TransactionBuilder4844,
+    {
+        let estimate = match tx.max_fee_per_blob_gas() {
+            Some(max_fee_per_blob_gas) if max_fee_per_blob_gas >= BLOB_TX_MIN_BLOB_GASPRICE => max_fee_per_blob_gas,
+            _ => {
+                let latest_block = provider
+                    .get_block_by_number(BlockNumberOrTag::Latest, BlockTransactionsKind::Hashes)
+                    .await?
+                    .ok_or(RpcError::NullResp)?;
+
+                latest_block
+                    .header()
+                    .as_ref()
+                    .next_block_blob_fee()
+                    .ok_or(RpcError::UnsupportedFeature("eip4844"))?
+            }
+        };
+
+        let (Some(from), Some(nonce)) = (tx.from(), tx.nonce()) else {
+            return Ok(std::cmp::min(estimate, self.max_blob_fee));
+        };
 
-        // check for queued
+        let mut states = self.states.lock().unwrap();
+        let Some(state) = states.get_mut(&(from, nonce)) else {
+            return Ok(std::cmp::min(estimate, self.max_blob_fee));
+        };
 
+        let blob_fee = match state.blob_fee {
+            Some(previous) => {
+                // a replacement capped below the bump would be rejected by the node
+                let required = previous.saturating_mul(BLOB_REPLACEMENT_MULTIPLE);
+                if required > self.max_blob_fee {
+                    return Err(GasError::ReplacementUnderpriced { from, nonce, required, cap: self.max_blob_fee }.into());
+                }
+                std::cmp::min(std::cmp::max(estimate, required), self.max_blob_fee)
+            }
+            None => std::cmp::min(estimate, self.max_blob_fee),
+        };
+        state.blob_fee = Some(blob_fee);
 
-        Ok(None)
+        Ok(blob_fee)
     }
 
+    /// Prepares the EIP-1559 fees of `tx`, replacing a pooled transaction by the rules of blob
+    /// transactions if `blob` is set.
     async fn prepare_1559<P, T, N>(
         &self,
         provider: &P,
         tx: &N::TransactionRequest,
+        blob: bool,
     ) -> TransportResult<GasFillable>
     where
         P: Provider<T, N>,
         T: Transport + Clone,
         N: Network,
     {
+        let requested_fees = tx.max_fee_per_gas().is_some() && tx.max_priority_fee_per_gas().is_some();
         let eip1559_fees_fut = if let (Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) =
             (tx.max_fee_per_gas(), tx.max_priority_fee_per_gas())
         {
@@ -116,38 +802,95 @@ impl GasEscalatorFiller {
             |gas_limit| async move { Ok(gas_limit) }.left_future(),
         );
 
-        let (gas_limit, default_estimate) = futures::try_join!(gas_limit_fut, eip1559_fees_fut)?;
-        let base_fee = default_estimate.max_fee_per_gas - default_estimate.max_priority_fee_per_gas;
-        // 10% increase minimum recommended by RPC providers
-        let replacement_fee = (default_estimate.max_fee_per_gas * 110) / 100;
-        let replacement_priority_fee = replacement_fee - base_fee;
-
-        let estimate = if let Some(tx_hash) = self.get_transaction(provider, tx).await? {
-            let current_bid = self.escalator.current_bid();
-            let bid_block = self.escalator.start_block + ((current_bid - self.escalator.start_bid) / self.escalator.increment) as u64;
-            let current_block = provider.get_block_number().await?;
-
-            let new_bid = if current_block != bid_block {
-                self.escalator.update_bid(current_block)
-            } else {
-                current_bid
-            };
-            
-            let max_priority_fee_per_gas = std::cmp::max(new_bid, replacement_priority_fee);
-            let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
+        let next_base_fee_fut = match self.oracle {
+            // replaced by the oracle's aggregated base fee
+            Some(_) => async { Ok(0) }.left_future(),
+            None => next_base_fee(provider, self.base_fee_params).right_future(),
+        };
 
-            println!(
-                "Retrying transaction {} with increased bid: {} wei",
-                tx_hash, new_bid
-            );
+        let (gas_limit, default_estimate, next_base_fee) = futures::try_join!(
+            gas_limit_fut,
+            eip1559_fees_fut,
+            next_base_fee_fut,
+        )?;
+        let ctx = self.find_pending(provider, tx).await?;
+        let oracle_fees = match self.oracle {
+            Some(_) => self.market_fees(provider, &ctx).await?,
+            None => None,
+        };
+        let next_base_fee = oracle_fees.map_or(next_base_fee, |fees| fees.base_fee);
+        let (replacement_fee, replacement_priority_fee) = self.min_replacement_fees(
+            &ctx,
+            (default_estimate.max_fee_per_gas, default_estimate.max_priority_fee_per_gas),
+            blob,
+        );
 
-            Eip1559Estimation {
-                max_fee_per_gas,
-                max_priority_fee_per_gas,
+        let estimate = match ctx.pending_tx {
+            Some(ref pooled) => match self.escalate_pooled(&ctx, pooled, replacement_priority_fee)? {
+                Some(mut new_bid) => {
+                    if let Some(tip) = self.competitive_tip(provider, &ctx, next_base_fee).await? {
+                        new_bid = self.raise_bid(&ctx, tip);
+                    }
+                    let base_fee = self.max_base_fee(&ctx, next_base_fee);
+                    let max_priority_fee_per_gas = std::cmp::max(new_bid, replacement_priority_fee);
+                    let max_fee_per_gas = std::cmp::max(base_fee.saturating_add(max_priority_fee_per_gas), replacement_fee);
+
+                    Eip1559Estimation {
+                        max_fee_per_gas,
+                        max_priority_fee_per_gas,
+                    }
+                }
+                None => Eip1559Estimation {
+                    max_fee_per_gas: std::cmp::max(default_estimate.max_fee_per_gas, replacement_fee),
+                    max_priority_fee_per_gas: std::cmp::max(
+                        default_estimate.max_priority_fee_per_gas,
+                        replacement_priority_fee,
+                    ),
+                },
+            },
+            None if requested_fees => {
+                // the first broadcast keeps the fees the caller asked for
+                self.next_bid(ctx.from, ctx.nonce, ctx.current_block, false);
+                default_estimate
+            }
+            None => {
+                let fees = match oracle_fees {
+                    Some(fees) => Some(fees),
+                    None => self.market_fees(provider, &ctx).await?,
+                };
+                // outbidding the mempool takes precedence over the market's percentile
+                let start_bid = match self.competitive_tip(provider, &ctx, next_base_fee).await? {
+                    Some(tip) => Some(tip),
+                    None => fees.and_then(|fees| fees.priority_fee),
+                };
+
+                if fees.is_none() && start_bid.is_none() {
+                    self.next_bid(ctx.from, ctx.nonce, ctx.current_block, false);
+                    let base_fee = self.max_base_fee(&ctx, next_base_fee);
+                    Eip1559Estimation {
+                        max_fee_per_gas: base_fee.saturating_add(default_estimate.max_priority_fee_per_gas),
+                        max_priority_fee_per_gas: default_estimate.max_priority_fee_per_gas,
+                    }
+                } else {
+                    let start_bid = start_bid.unwrap_or(default_estimate.max_priority_fee_per_gas);
+                    let urgency = self.urgency(ctx.from, ctx.nonce);
+                    let bid = self.start_escalation(&ctx, |escalator| match (urgency, fees) {
+                        (Some(urgency), Some(fees)) => {
+                            let preset = urgency.preset();
+                            escalator.apply_urgency(&preset, start_bid, preset.max_bid(fees.base_fee));
+                        }
+                        _ => escalator.set_start_bid(start_bid),
+                    });
+
+                    println!("Starting transaction {}/{} at market bid: {} wei", ctx.from, ctx.nonce, bid);
+                    let base_fee = self.max_base_fee(&ctx, next_base_fee);
+                    Eip1559Estimation { max_fee_per_gas: base_fee.saturating_add(bid), max_priority_fee_per_gas: bid }
+                }
             }
-        } else {
-            default_estimate
         };
+        let min_fee_per_gas = if ctx.pending_tx.is_some() { replacement_fee } else { 0 };
+        let estimate = self.limit_spend(&ctx, gas_limit, estimate, min_fee_per_gas, next_base_fee)?;
+        self.record_sent(&ctx, estimate.max_fee_per_gas, estimate.max_priority_fee_per_gas);
 
         println!("🚀 Gas Estimate: {:?}", estimate);
 
@@ -155,11 +898,19 @@ impl GasEscalatorFiller {
     }
 }
 
-impl<N: Network> TxFiller<N> for GasEscalatorFiller {
-    type Fillable = GasFillable;
+impl<N: Network, E: Escalator> TxFiller<N> for GasEscalatorFiller<E>
+where
+    N::TransactionRequest: TransactionBuilder4844,
+{
+    type Fillable = EscalatorFillable;
 
     // from gas.rs
     fn status(&self, tx: &<N as Network>::TransactionRequest) -> FillerControlFlow {
+        // eip4844 tx without a blob fee
+        if tx.blob_sidecar().is_some() && tx.max_fee_per_blob_gas().is_none() {
+            return FillerControlFlow::Ready;
+        }
+
         // legacy and eip2930 tx
         if tx.gas_price().is_some() && tx.gas_limit().is_some() {
             return FillerControlFlow::Finished;
@@ -187,7 +938,35 @@ impl<N: Network> TxFiller<N> for GasEscalatorFiller {
         P: Provider<T, N>,
         T: Transport + Clone,
     {
-        self.prepare_1559(provider, tx).await
+        if tx.blob_sidecar().is_some() {
+            let gas = self.prepare_1559(provider, tx, true).await?;
+            if let (Some(from), Some(nonce)) = (tx.from(), tx.nonce()) {
+                // blob transactions can only be replaced by other blob transactions
+                if self.is_cancelled(from, nonce) {
+                    return Err(GasError::EscalationExpired { from, nonce }.into());
+                }
+            }
+            let max_fee_per_blob_gas = self.prepare_blob_fee(provider, tx).await?;
+            return Ok(EscalatorFillable {
+                gas,
+                max_fee_per_blob_gas: Some(max_fee_per_blob_gas),
+                cancel: false,
+            });
+        }
+
+        let gas = if tx.gas_price().is_some() {
+            self.prepare_legacy(provider, tx).await?
+        } else {
+            match self.prepare_1559(provider, tx, false).await {
+                // fallback to legacy
+                Ok(estimate) => estimate,
+                Err(RpcError::UnsupportedFeature(_)) => self.prepare_legacy(provider, tx).await?,
+                Err(e) => return Err(e),
+            }
+        };
+
+        let cancel = tx.from().zip(tx.nonce()).is_some_and(|(from, nonce)| self.is_cancelled(from, nonce));
+        Ok(EscalatorFillable { gas, max_fee_per_blob_gas: None, cancel })
     }
 
     async fn fill(
@@ -196,7 +975,11 @@ impl<N: Network> TxFiller<N> for GasEscalatorFiller {
         mut tx: SendableTx<N>,
     ) -> TransportResult<SendableTx<N>> {
         if let Some(builder) = tx.as_mut_builder() {
-            match fillable {
+            if let Some(max_fee_per_blob_gas) = fillable.max_fee_per_blob_gas {
+                builder.set_max_fee_per_blob_gas(max_fee_per_blob_gas);
+            }
+
+            match fillable.gas {
                 GasFillable::Legacy { gas_limit, gas_price } => {
                     builder.set_gas_limit(gas_limit);
                     builder.set_gas_price(gas_price);
@@ -207,11 +990,28 @@ impl<N: Network> TxFiller<N> for GasEscalatorFiller {
                     builder.set_max_priority_fee_per_gas(estimate.max_priority_fee_per_gas);
                 }
             }
+
+            if fillable.cancel {
+                if let Some(from) = builder.from() {
+                    builder.set_to(from);
+                }
+                builder.set_value(U256::ZERO);
+                builder.set_input(Bytes::new());
+                builder.set_access_list(Default::default());
+                builder.set_gas_limit(CANCEL_GAS_LIMIT);
+            }
         };
         Ok(tx)
     }
 }
 
+/// Returns `true` if `tx` is a cancellation, a 0-value self-transfer without calldata.
+fn is_cancellation<R: TransactionBuilder<N>, N: Network>(tx: &R) -> bool {
+    tx.from().is_some_and(|from| tx.to() == Some(from))
+        && tx.value().unwrap_or_default().is_zero()
+        && tx.input().is_none_or(|input| input.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     mod esclator_tests;
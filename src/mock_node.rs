@@ -0,0 +1,83 @@
+use std::{borrow::Cow, collections::HashMap, sync::{Arc, Mutex}, task::{Context, Poll}};
+
+use alloy_json_rpc::{ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest};
+use alloy_provider::{ProviderBuilder, RootProvider};
+use alloy_rpc_client::RpcClient;
+use alloy_transport::{TransportError, TransportFut};
+use serde_json::value::RawValue;
+use tower::Service;
+
+/// Answer of the [`MockNode`] to a JSON-RPC method.
+#[derive(Clone)]
+enum Answer {
+    Result(serde_json::Value),
+    Error(i64, String),
+}
+
+/// In-process JSON-RPC node answering every method with a canned result, for tests that need
+/// control over what the node supports.
+#[derive(Clone, Default)]
+pub struct MockNode {
+    answers: Arc<Mutex<HashMap<String, Answer>>>,
+    calls: Arc<Mutex<Vec<String>>>, // Methods called, in order
+}
+
+#[allow(dead_code)]
+impl MockNode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers `method` with `result`.
+    pub fn respond(&self, method: &str, result: serde_json::Value) {
+        self.answers.lock().unwrap().insert(method.to_string(), Answer::Result(result));
+    }
+
+    /// Answers `method` with a JSON-RPC error, as a node without it would.
+    pub fn reject(&self, method: &str) {
+        let message = format!("the method {method} does not exist/is not available");
+        self.answers.lock().unwrap().insert(method.to_string(), Answer::Error(-32601, message));
+    }
+
+    /// Returns how many times `method` was called.
+    pub fn calls(&self, method: &str) -> usize {
+        self.calls.lock().unwrap().iter().filter(|called| *called == method).count()
+    }
+
+    pub fn provider(&self) -> RootProvider<MockNode> {
+        ProviderBuilder::new().on_client(RpcClient::new(self.clone(), true))
+    }
+
+    fn answer(&self, request: &SerializedRequest) -> Response {
+        let method = request.method().to_string();
+        self.calls.lock().unwrap().push(method.clone());
+
+        let answer = self.answers.lock().unwrap().get(&method).cloned();
+        let payload = match answer {
+            Some(Answer::Result(result)) => ResponsePayload::Success(RawValue::from_string(result.to_string()).unwrap()),
+            Some(Answer::Error(code, message)) => {
+                ResponsePayload::Failure(ErrorPayload { code, message: Cow::Owned(message), data: None })
+            }
+            None => panic!("unexpected call to {method}"),
+        };
+        Response { id: request.id().clone(), payload }
+    }
+}
+
+impl Service<RequestPacket> for MockNode {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match request {
+            RequestPacket::Single(request) => ResponsePacket::Single(self.answer(&request)),
+            RequestPacket::Batch(requests) => ResponsePacket::Batch(requests.iter().map(|r| self.answer(r)).collect()),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
@@ -0,0 +1,200 @@
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_network::Network;
+use alloy_provider::Provider;
+use alloy_transport::{RpcError, Transport, TransportResult};
+
+use crate::{estimator::next_base_fee, txpool::pending_tips, FeeEstimate, FeeHistoryEstimator};
+
+/// A source of market fees consulted by [`GasEscalatorFiller`](crate::GasEscalatorFiller) to
+/// price the first bid of a transaction.
+#[async_trait::async_trait]
+pub trait GasOracle: Debug + Send + Sync {
+    /// Returns the next block's base fee and the priority fee paid at `percentile` (0 to 100)
+    /// of the market.
+    async fn estimate(&self, percentile: f64) -> TransportResult<FeeEstimate>;
+}
+
+/// [`GasOracle`] reading the reward percentiles of a node's `eth_feeHistory`.
+pub struct FeeHistoryOracle<P, T, N> {
+    provider: P,
+    block_count: u64, // Number of recent blocks to sample
+    _marker: PhantomData<fn() -> (T, N)>,
+}
+
+impl<P, T, N> FeeHistoryOracle<P, T, N> {
+    pub fn new(provider: P, block_count: u64) -> Self {
+        Self { provider, block_count, _marker: PhantomData }
+    }
+}
+
+impl<P, T, N> Debug for FeeHistoryOracle<P, T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeeHistoryOracle").field("block_count", &self.block_count).finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl<P, T, N> GasOracle for FeeHistoryOracle<P, T, N>
+where
+    P: Provider<T, N>,
+    T: Transport + Clone,
+    N: Network,
+{
+    async fn estimate(&self, percentile: f64) -> TransportResult<FeeEstimate> {
+        FeeHistoryEstimator::new(self.block_count, percentile).estimate(&self.provider).await
+    }
+}
+
+/// [`GasOracle`] reading the tips offered by the executable transactions of a node's mempool,
+/// through `txpool_content`.
+pub struct MempoolOracle<P, T, N> {
+    provider: P,
+    base_fee_params: BaseFeeParams,
+    _marker: PhantomData<fn() -> (T, N)>,
+}
+
+impl<P, T, N> MempoolOracle<P, T, N> {
+    pub fn new(provider: P) -> Self {
+        Self::with_base_fee_params(provider, BaseFeeParams::ethereum())
+    }
+
+    pub fn with_base_fee_params(provider: P, base_fee_params: BaseFeeParams) -> Self {
+        Self { provider, base_fee_params, _marker: PhantomData }
+    }
+}
+
+impl<P, T, N> Debug for MempoolOracle<P, T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MempoolOracle").field("base_fee_params", &self.base_fee_params).finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl<P, T, N> GasOracle for MempoolOracle<P, T, N>
+where
+    P: Provider<T, N>,
+    T: Transport + Clone,
+    N: Network,
+{
+    async fn estimate(&self, percentile: f64) -> TransportResult<FeeEstimate> {
+        let base_fee = next_base_fee(&self.provider, self.base_fee_params).await?;
+        let mut tips: Vec<u128> =
+            pending_tips(&self.provider, base_fee, None).await?.into_iter().map(|(tip, _)| tip).collect();
+        if tips.is_empty() {
+            return Ok(FeeEstimate { base_fee, priority_fee: None });
+        }
+
+        tips.sort_unstable();
+        let index = std::cmp::min((percentile / 100.0 * tips.len() as f64) as usize, tips.len() - 1);
+        Ok(FeeEstimate { base_fee, priority_fee: Some(tips[index]) })
+    }
+}
+
+/// [`GasOracle`] returning configured fees, e.g. from an external fee API or as a floor.
+#[derive(Clone, Copy, Debug)]
+pub struct StaticOracle {
+    estimate: FeeEstimate,
+}
+
+impl StaticOracle {
+    pub fn new(base_fee: u128, priority_fee: u128) -> Self {
+        Self { estimate: FeeEstimate { base_fee, priority_fee: Some(priority_fee) } }
+    }
+}
+
+#[async_trait::async_trait]
+impl GasOracle for StaticOracle {
+    async fn estimate(&self, _percentile: f64) -> TransportResult<FeeEstimate> {
+        Ok(self.estimate)
+    }
+}
+
+/// How [`AggregateOracle`] combines the estimates of its sources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Aggregation {
+    /// The median estimate, so a single outlier cannot move the result.
+    #[default]
+    Median,
+    /// The highest estimate.
+    Max,
+    /// The mean of the estimates, weighted by the weight of their source.
+    Weighted,
+}
+
+/// [`GasOracle`] combining several sources. Sources that fail are skipped, so the estimate only
+/// fails if all of them do.
+#[derive(Clone, Debug, Default)]
+pub struct AggregateOracle {
+    oracles: Vec<(Arc<dyn GasOracle>, u64)>, // Sources and their weights
+    aggregation: Aggregation,
+}
+
+impl AggregateOracle {
+    pub fn new(aggregation: Aggregation) -> Self {
+        Self { oracles: Vec::new(), aggregation }
+    }
+
+    /// Adds a source with weight 1.
+    pub fn with_oracle(self, oracle: impl GasOracle + 'static) -> Self {
+        self.with_weighted_oracle(oracle, 1)
+    }
+
+    /// Adds a source whose estimates count `weight` times in [`Aggregation::Weighted`].
+    pub fn with_weighted_oracle(mut self, oracle: impl GasOracle + 'static, weight: u64) -> Self {
+        self.oracles.push((Arc::new(oracle), weight));
+        self
+    }
+
+    /// Combines `values`, each with the weight of its source.
+    pub fn aggregate(&self, mut values: Vec<(u128, u64)>) -> Option<u128> {
+        if values.is_empty() {
+            return None;
+        }
+
+        match self.aggregation {
+            Aggregation::Median => {
+                values.sort_unstable();
+                Some(values[values.len() / 2].0)
+            }
+            Aggregation::Max => values.into_iter().map(|(value, _)| value).max(),
+            Aggregation::Weighted => {
+                let total_weight: u128 = values.iter().map(|(_, weight)| *weight as u128).sum();
+                if total_weight == 0 {
+                    return None;
+                }
+                let sum: u128 = values.iter().map(|(value, weight)| value.saturating_mul(*weight as u128)).sum();
+                Some(sum / total_weight)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl GasOracle for AggregateOracle {
+    async fn estimate(&self, percentile: f64) -> TransportResult<FeeEstimate> {
+        let results =
+            futures::future::join_all(self.oracles.iter().map(|(oracle, _)| oracle.estimate(percentile))).await;
+
+        let mut base_fees = Vec::new();
+        let mut priority_fees = Vec::new();
+        let mut last_error = None;
+        for ((oracle, weight), result) in self.oracles.iter().zip(results) {
+            match result {
+                Ok(estimate) => {
+                    base_fees.push((estimate.base_fee, *weight));
+                    priority_fees.extend(estimate.priority_fee.map(|fee| (fee, *weight)));
+                }
+                Err(e) => {
+                    println!("Gas oracle {:?} failed: {:?}", oracle, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match self.aggregate(base_fees) {
+            Some(base_fee) => Ok(FeeEstimate { base_fee, priority_fee: self.aggregate(priority_fees) }),
+            None => Err(last_error.unwrap_or(RpcError::NullResp)),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+use alloy_network::{Network, TransactionBuilder};
+use alloy_primitives::{Address, B256, U256};
+use alloy_provider::Provider;
+use alloy_transport::{Transport, TransportResult};
+use tokio::sync::oneshot;
+
+use crate::{budget::{receipt_fee, Budget}, Clock, GasBudget, GasError, CANCEL_GAS_LIMIT};
+
+/// Handle to a transaction that is rebroadcast in the background until one of its
+/// replacements is mined.
+pub struct EscalationHandle<N: Network> {
+    from: Address,
+    nonce: u64,
+    tx_hashes: Arc<Mutex<Vec<B256>>>,
+    receipt: oneshot::Receiver<TransportResult<N::ReceiptResponse>>,
+    budget: Option<Arc<dyn Budget>>, // Records the fees paid once mined
+}
+
+impl<N: Network> EscalationHandle<N> {
+    /// Records the fees paid by the transaction against `budget` once it is mined, and
+    /// releases what the filler reserved for it.
+    pub fn with_budget<C: Clock + 'static>(mut self, budget: GasBudget<C>) -> Self {
+        self.budget = Some(Arc::new(budget));
+        self
+    }
+
+    /// Returns the hashes of every broadcast version of the transaction, oldest first.
+    pub fn tx_hashes(&self) -> Vec<B256> {
+        self.tx_hashes.lock().unwrap().clone()
+    }
+
+    /// Waits until one of the broadcast versions of the transaction is mined.
+    pub async fn get_receipt(self) -> TransportResult<N::ReceiptResponse> {
+        let result = self.receipt.await.unwrap_or_else(|_| Err(GasError::RebroadcastStopped.into()));
+
+        if let Some(budget) = &self.budget {
+            match &result {
+                Ok(receipt) => {
+                    budget.record_spend(self.from, receipt_fee(receipt));
+                    budget.release(self.from, self.nonce);
+                }
+                // mined by a transaction this budget did not pay for
+                Err(e) if matches!(GasError::from_transport_error(e), Some(GasError::NonceConsumed { .. })) => {
+                    budget.release(self.from, self.nonce);
+                }
+                Err(_) => {}
+            }
+        }
+
+        result
+    }
+}
+
+/// Provider extension that keeps escalating a transaction without the caller re-sending it.
+///
+/// The provider is expected to be layered with a
+/// [`GasEscalatorFiller`](crate::GasEscalatorFiller): every new block the transaction is
+/// re-sent with the same nonce, so the filler re-signs it with the next bid.
+#[async_trait::async_trait]
+pub trait EscalatorProviderExt<T, N: Network>: Send + Sync {
+    /// Sends `tx` and spawns a task rebroadcasting it on every new block until it is mined.
+    ///
+    /// The fee fields of `tx` should be left unset so each rebroadcast is re-priced.
+    async fn send_escalating_transaction(
+        &self,
+        tx: N::TransactionRequest,
+    ) -> TransportResult<EscalationHandle<N>>;
+
+    /// Abandons the transaction sent by `from` with `nonce` by replacing it with a 0-value
+    /// self-transfer, escalated like any other transaction until the nonce is consumed.
+    ///
+    /// The handle resolves to the receipt of the cancellation, or fails with
+    /// [`GasError::NonceConsumed`] if the original transaction was mined first. The filler
+    /// refuses further sends of the original, so a task still rebroadcasting it stops re-sending
+    /// and resolves once the nonce is consumed.
+    async fn cancel_transaction(
+        &self,
+        from: Address,
+        nonce: u64,
+    ) -> TransportResult<EscalationHandle<N>>;
+}
+
+#[async_trait::async_trait]
+impl<P, T, N> EscalatorProviderExt<T, N> for P
+where
+    P: Provider<T, N> + Clone + 'static,
+    T: Transport + Clone,
+    N: Network,
+{
+    async fn send_escalating_transaction(
+        &self,
+        mut tx: N::TransactionRequest,
+    ) -> TransportResult<EscalationHandle<N>> {
+        let from = tx.from().ok_or(GasError::MissingFrom)?;
+        let nonce = match tx.nonce() {
+            Some(nonce) => nonce,
+            None => self.get_transaction_count(from).pending().await?,
+        };
+        // every rebroadcast must replace the same nonce
+        tx.set_nonce(nonce);
+
+        let start_block = self.get_block_number().await?;
+        let pending = self.send_transaction(tx.clone()).await?;
+        let tx_hashes = Arc::new(Mutex::new(vec![*pending.tx_hash()]));
+
+        let (sender, receipt) = oneshot::channel();
+        let task = Rebroadcast {
+            provider: self.clone(),
+            tx,
+            from,
+            nonce,
+            last_block: start_block,
+            poll_interval: self.client().poll_interval(),
+            tx_hashes: tx_hashes.clone(),
+            resending: true,
+        };
+        tokio::spawn(task.run(sender));
+
+        Ok(EscalationHandle { from, nonce, tx_hashes, receipt, budget: None })
+    }
+
+    async fn cancel_transaction(
+        &self,
+        from: Address,
+        nonce: u64,
+    ) -> TransportResult<EscalationHandle<N>> {
+        // the filler prices it above the pending transaction to satisfy the replacement rule
+        let tx = N::TransactionRequest::default()
+            .with_from(from)
+            .with_to(from)
+            .with_value(U256::ZERO)
+            .with_nonce(nonce)
+            .with_gas_limit(CANCEL_GAS_LIMIT);
+
+        println!("Cancelling transaction {}/{}", from, nonce);
+        self.send_escalating_transaction(tx).await
+    }
+}
+
+pub(crate) enum Outcome<R> {
+    Pending,
+    Mined(R),
+    NonceConsumed,
+}
+
+/// Returns the final result of the rebroadcast of `(from, nonce)` given the outcome of a poll,
+/// or `None` if it should keep polling.
+///
+/// Transport errors are retried on the next poll, but a [`GasError`] raised while pricing a
+/// transaction that was never broadcast, e.g. [`GasError::BudgetExhausted`], can never succeed
+/// and ends it.
+pub(crate) fn settle<R>(from: Address, nonce: u64, poll: TransportResult<Outcome<R>>) -> Option<TransportResult<R>> {
+    match poll {
+        Ok(Outcome::Pending) => None,
+        Ok(Outcome::Mined(receipt)) => Some(Ok(receipt)),
+        Ok(Outcome::NonceConsumed) => Some(Err(GasError::NonceConsumed { from, nonce }.into())),
+        Err(e) if GasError::from_transport_error(&e).is_some() => {
+            println!("Stopped rebroadcasting transaction {}/{}: {}", from, nonce, e);
+            Some(Err(e))
+        }
+        Err(e) => {
+            println!("Failed to poll transaction {}/{}: {:?}", from, nonce, e);
+            None
+        }
+    }
+}
+
+struct Rebroadcast<P, N: Network> {
+    provider: P,
+    tx: N::TransactionRequest,
+    from: Address,
+    nonce: u64,
+    last_block: u64,
+    poll_interval: Duration,
+    tx_hashes: Arc<Mutex<Vec<B256>>>,
+    resending: bool, // Cleared once the filler refuses a resend
+}
+
+impl<P, N: Network> Rebroadcast<P, N> {
+    async fn run<T>(mut self, mut sender: oneshot::Sender<TransportResult<N::ReceiptResponse>>)
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+    {
+        loop {
+            tokio::select! {
+                _ = sender.closed() => return,
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+
+            let poll = self.poll().await;
+            if let Some(result) = settle(self.from, self.nonce, poll) {
+                let _ = sender.send(result);
+                return;
+            }
+        }
+    }
+
+    async fn poll<T>(&mut self) -> TransportResult<Outcome<N::ReceiptResponse>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+    {
+        if self.provider.get_transaction_count(self.from).await? > self.nonce {
+            let tx_hashes = self.tx_hashes.lock().unwrap().clone();
+            for tx_hash in tx_hashes {
+                if let Some(receipt) = self.provider.get_transaction_receipt(tx_hash).await? {
+                    return Ok(Outcome::Mined(receipt));
+                }
+            }
+            return Ok(Outcome::NonceConsumed);
+        }
+
+        if !self.resending {
+            return Ok(Outcome::Pending);
+        }
+        let current_block = self.provider.get_block_number().await?;
+        if current_block <= self.last_block {
+            return Ok(Outcome::Pending);
+        }
+        self.last_block = current_block;
+
+        let pending = match self.provider.send_transaction(self.tx.clone()).await {
+            Ok(pending) => pending,
+            // the last broadcast version is still in the mempool and may be mined
+            Err(e) if GasError::from_transport_error(&e).is_some() && !self.tx_hashes.lock().unwrap().is_empty() => {
+                println!("Stopped re-sending transaction {}/{}: {}, waiting for it to be mined", self.from, self.nonce, e);
+                self.resending = false;
+                return Ok(Outcome::Pending);
+            }
+            Err(e) => return Err(e),
+        };
+        println!("Rebroadcast transaction {} at block {}", pending.tx_hash(), current_block);
+        self.tx_hashes.lock().unwrap().push(*pending.tx_hash());
+
+        Ok(Outcome::Pending)
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+/// Minimum fee increase nodes require before accepting a transaction that replaces one
+/// already in their mempool.
+///
+/// geth, reth, nethermind and erigon all default to a 10% bump of both the fee cap and the
+/// tip, and reject replacements that do not strictly increase either of them.
+#[derive(Clone, Debug)]
+pub struct ReplacementPolicy {
+    bump_percent: u128,
+    chain_bumps: HashMap<u64, u128>, // Per-chain overrides of `bump_percent`
+}
+
+impl Default for ReplacementPolicy {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl ReplacementPolicy {
+    pub fn new(bump_percent: u128) -> Self {
+        Self { bump_percent, chain_bumps: HashMap::new() }
+    }
+
+    /// Overrides the bump required on chain `chain_id`.
+    pub fn with_chain_bump(mut self, chain_id: u64, bump_percent: u128) -> Self {
+        self.chain_bumps.insert(chain_id, bump_percent);
+        self
+    }
+
+    /// Returns `true` if the bump depends on the chain the transaction is sent to.
+    pub fn has_chain_overrides(&self) -> bool {
+        !self.chain_bumps.is_empty()
+    }
+
+    /// Returns the bump in percent required on chain `chain_id`.
+    pub fn bump_percent(&self, chain_id: Option<u64>) -> u128 {
+        chain_id.and_then(|chain_id| self.chain_bumps.get(&chain_id).copied()).unwrap_or(self.bump_percent)
+    }
+
+    /// Returns the lowest fee that replaces a transaction paying `fee` on chain `chain_id`.
+    pub fn min_replacement_fee(&self, fee: u128, chain_id: Option<u64>) -> u128 {
+        let bumped = fee.saturating_mul(100 + self.bump_percent(chain_id)).div_ceil(100);
+        std::cmp::max(bumped, fee.saturating_add(1))
+    }
+}
@@ -0,0 +1,630 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+    sync::Arc,
+};
+use alloy_primitives::Address;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::Escalator;
+
+/// Tip assumed for blocks without any priced transaction, in wei.
+const FALLBACK_TIP: u128 = 1_000_000_000;
+
+/// Errors raised while loading a block dataset.
+#[derive(Debug, thiserror::Error)]
+pub enum SimulationError {
+    #[error("failed to read dataset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse dataset: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A historical block, as exported to `blocks.json`.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct BlockFeeData {
+    pub block_number: u64,
+    pub base_fee_per_gas: u128,
+    #[serde(default)]
+    pub gas_used: u64,
+}
+
+/// A historical transaction, as exported to `transactions.json`.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct TransactionData {
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub from_address: Address,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub gas_price: u128,
+}
+
+/// A historical block and the transactions it included.
+#[derive(Clone, Debug)]
+pub struct SimulatedBlock {
+    pub base_fee_per_gas: u128,
+    pub gas_used: u64,
+    pub transactions: Vec<TransactionData>,
+}
+
+impl SimulatedBlock {
+    /// Returns the tips paid by the block's transactions above its base fee, ascending.
+    pub fn tips(&self) -> Vec<u128> {
+        let mut tips: Vec<u128> = self.transactions.iter().map(|tx| self.tip(tx)).collect();
+        tips.sort_unstable();
+        tips
+    }
+
+    /// Returns the tip `tx` paid above the block's base fee.
+    pub fn tip(&self, tx: &TransactionData) -> u128 {
+        tx.gas_price.saturating_sub(self.base_fee_per_gas)
+    }
+}
+
+/// Historical blocks replayed by the [`Simulator`], by number.
+#[derive(Clone, Debug, Default)]
+pub struct BlockDataset {
+    blocks: BTreeMap<u64, SimulatedBlock>,
+}
+
+impl BlockDataset {
+    /// Builds a dataset from `blocks` and the `transactions` they included. Transactions of
+    /// unknown blocks are dropped.
+    pub fn new(blocks: Vec<BlockFeeData>, transactions: Vec<TransactionData>) -> Self {
+        let mut blocks: BTreeMap<u64, SimulatedBlock> = blocks
+            .into_iter()
+            .map(|block| {
+                let simulated = SimulatedBlock {
+                    base_fee_per_gas: block.base_fee_per_gas,
+                    gas_used: block.gas_used,
+                    transactions: Vec::new(),
+                };
+                (block.block_number, simulated)
+            })
+            .collect();
+
+        for tx in transactions {
+            if let Some(block) = blocks.get_mut(&tx.block_number) {
+                block.transactions.push(tx);
+            }
+        }
+
+        Self { blocks }
+    }
+
+    /// Loads a dataset from JSON exports of blocks and transactions.
+    pub fn load(blocks: impl AsRef<Path>, transactions: impl AsRef<Path>) -> Result<Self, SimulationError> {
+        Ok(Self::new(load_json(blocks)?, load_json(transactions)?))
+    }
+
+    pub fn block(&self, block_number: u64) -> Option<&SimulatedBlock> {
+        self.blocks.get(&block_number)
+    }
+
+    /// Returns the first and last block numbers of the dataset.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        Some((*self.blocks.keys().next()?, *self.blocks.keys().next_back()?))
+    }
+
+    /// Returns the tip paid at `percentile` of the transactions of block `block_number`, or
+    /// `None` if the block is unknown or did not include any priced transaction.
+    pub fn tip_percentile(&self, block_number: u64, percentile: f64) -> Option<u128> {
+        percentile_of(&self.block(block_number)?.tips(), percentile)
+    }
+}
+
+fn load_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, SimulationError> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Fees bid by a synthetic transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bid {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl Bid {
+    /// Returns the tip actually paid in a block with base fee `base_fee`, or `None` if the bid
+    /// cannot pay the base fee.
+    pub fn effective_tip(&self, base_fee: u128) -> Option<u128> {
+        let headroom = self.max_fee_per_gas.checked_sub(base_fee)?;
+        Some(std::cmp::min(self.max_priority_fee_per_gas, headroom))
+    }
+}
+
+/// How a synthetic transaction is priced while it waits for inclusion.
+pub trait Strategy: Debug {
+    fn name(&self) -> String;
+
+    /// Returns the fees of a transaction first sent at block `submitted_at` when competing for
+    /// block `block`, or `None` to leave its last bid unchanged (e.g. missing fee data).
+    fn bid(&self, dataset: &BlockDataset, submitted_at: u64, block: u64) -> Option<Bid>;
+
+    /// Returns whether a transaction first sent at block `submitted_at` is given up at block
+    /// `block`.
+    fn is_expired(&self, _submitted_at: u64, _block: u64) -> bool {
+        false
+    }
+}
+
+/// Prices the transaction once from the previous block's tips and never replaces it.
+#[derive(Clone, Debug)]
+pub struct NaiveStrategy {
+    percentile: f64, // Percentile of the previous block's tips
+}
+
+impl NaiveStrategy {
+    pub fn new(percentile: f64) -> Self {
+        Self { percentile }
+    }
+}
+
+impl Strategy for NaiveStrategy {
+    fn name(&self) -> String {
+        format!("naive-p{}", self.percentile)
+    }
+
+    fn bid(&self, dataset: &BlockDataset, submitted_at: u64, _block: u64) -> Option<Bid> {
+        let previous_block = submitted_at.checked_sub(1)?;
+        let tip = dataset.tip_percentile(previous_block, self.percentile).unwrap_or(FALLBACK_TIP);
+        let base_fee = dataset.block(previous_block)?.base_fee_per_gas;
+
+        Some(Bid { max_fee_per_gas: 2 * base_fee + tip, max_priority_fee_per_gas: tip })
+    }
+}
+
+/// Replaces the transaction every block with the bid of an [`Escalator`] restarted at the
+/// block it was first sent, and gives up once the escalator expires.
+#[derive(Clone, Debug)]
+pub struct EscalatorStrategy<E> {
+    name: String,
+    escalator: E,
+}
+
+impl<E: Escalator> EscalatorStrategy<E> {
+    pub fn new(name: impl Into<String>, escalator: E) -> Self {
+        Self { name: name.into(), escalator }
+    }
+}
+
+impl<E: Escalator> Strategy for EscalatorStrategy<E> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn bid(&self, dataset: &BlockDataset, submitted_at: u64, block: u64) -> Option<Bid> {
+        let mut escalator = self.escalator.clone();
+        escalator.reset(submitted_at);
+
+        let tip = escalator.bid(block);
+        let base_fee = dataset.block(block.checked_sub(1)?)?.base_fee_per_gas;
+        Some(Bid { max_fee_per_gas: 2 * base_fee + tip, max_priority_fee_per_gas: tip })
+    }
+
+    fn is_expired(&self, submitted_at: u64, block: u64) -> bool {
+        let mut escalator = self.escalator.clone();
+        escalator.reset(submitted_at);
+        escalator.is_expired(block)
+    }
+}
+
+/// Outcome of a synthetic transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulatedTransaction {
+    pub submitted_at: u64,
+    pub included_at: Option<u64>,  // None if it expired or was still pending at the end
+    pub expired: bool,
+    pub bid: Option<Bid>,          // Last bid sent
+    pub effective_tip: Option<u128>, // Tip paid per gas once included
+    pub fee_paid: u128,            // Total fee paid once included, in wei
+}
+
+impl SimulatedTransaction {
+    /// Returns the blocks waited between the first broadcast and the inclusion.
+    pub fn latency(&self) -> Option<u64> {
+        Some(self.included_at? - self.submitted_at)
+    }
+}
+
+/// Transactions of a strategy replayed by the [`Simulator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimulationResult {
+    pub strategy: String,
+    pub transactions: Vec<SimulatedTransaction>,
+}
+
+impl SimulationResult {
+    pub fn included(&self) -> impl Iterator<Item = &SimulatedTransaction> {
+        self.transactions.iter().filter(|tx| tx.included_at.is_some())
+    }
+
+    /// Returns the blocks every included transaction waited, in submission order.
+    pub fn latencies(&self) -> Vec<u64> {
+        self.included().filter_map(SimulatedTransaction::latency).collect()
+    }
+
+    /// Returns the fees paid by every included transaction, in wei.
+    pub fn total_fees(&self) -> u128 {
+        self.included().map(|tx| tx.fee_paid).sum()
+    }
+
+    /// Returns the mean blocks waited by the included transactions, or `None` if none was.
+    pub fn mean_latency(&self) -> Option<f64> {
+        let latencies = self.latencies();
+        (!latencies.is_empty()).then(|| latencies.iter().sum::<u64>() as f64 / latencies.len() as f64)
+    }
+}
+
+/// Percentiles of a distribution, from the smallest to the largest value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Percentiles<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+    pub max: T,
+}
+
+impl<T: Copy + Ord> Percentiles<T> {
+    /// Returns the percentiles of `values`, or `None` if it is empty.
+    pub fn of(mut values: Vec<T>) -> Option<Self> {
+        values.sort_unstable();
+        Some(Self {
+            p50: percentile_of(&values, 50.0)?,
+            p90: percentile_of(&values, 90.0)?,
+            p99: percentile_of(&values, 99.0)?,
+            max: *values.last()?,
+        })
+    }
+}
+
+/// Returns the value at `percentile` of the ascending `values`.
+fn percentile_of<T: Copy>(values: &[T], percentile: f64) -> Option<T> {
+    let last = values.len().checked_sub(1)?;
+    Some(values[std::cmp::min((percentile / 100.0 * values.len() as f64) as usize, last)])
+}
+
+/// Inclusion delay and cost of a strategy, built by [`Simulator::report`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub strategy: String,
+    pub submitted: usize,
+    pub included: usize,
+    pub expired: usize,
+    pub pending: usize,                       // Neither included nor expired at the end
+    pub inclusion_rate: f64,
+    pub mean_latency: Option<f64>,            // Blocks to inclusion
+    pub latency: Option<Percentiles<u64>>,
+    pub latency_histogram: BTreeMap<u64, usize>, // Included transactions by blocks to inclusion
+    pub mean_tip: Option<u128>,               // Effective tip, in wei per gas
+    pub tip: Option<Percentiles<u128>>,
+    pub total_fees: u128,                     // In wei
+    pub overpayment: u128,                    // Above the hindsight-minimum tip, in wei
+}
+
+impl SimulationReport {
+    const CSV_HEADER: &'static str = "strategy,submitted,included,expired,pending,inclusion_rate,\
+        mean_latency,latency_p50,latency_p90,latency_p99,latency_max,\
+        mean_tip,tip_p50,tip_p90,tip_p99,tip_max,total_fees,overpayment";
+
+    /// Writes `reports` as a JSON array.
+    pub fn write_json(reports: &[SimulationReport], writer: impl Write) -> Result<(), SimulationError> {
+        Ok(serde_json::to_writer_pretty(writer, reports)?)
+    }
+
+    /// Writes `reports` as CSV, one row per strategy. The latency histogram is only exported
+    /// to JSON.
+    pub fn write_csv(reports: &[SimulationReport], mut writer: impl Write) -> Result<(), SimulationError> {
+        writeln!(writer, "{}", Self::CSV_HEADER)?;
+        for report in reports {
+            writeln!(writer, "{}", report.csv_row())?;
+        }
+        Ok(())
+    }
+
+    fn csv_row(&self) -> String {
+        fn cell<T: ToString>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+        fn cells<T: Copy + ToString>(percentiles: Option<Percentiles<T>>) -> [String; 4] {
+            let values = percentiles.map(|p| [p.p50, p.p90, p.p99, p.max]);
+            match values {
+                Some(values) => values.map(|value| value.to_string()),
+                None => Default::default(),
+            }
+        }
+
+        let strategy = if self.strategy.contains([',', '"']) {
+            format!("\"{}\"", self.strategy.replace('"', "\"\""))
+        } else {
+            self.strategy.clone()
+        };
+
+        let mut row = vec![
+            strategy,
+            self.submitted.to_string(),
+            self.included.to_string(),
+            self.expired.to_string(),
+            self.pending.to_string(),
+            self.inclusion_rate.to_string(),
+            cell(self.mean_latency),
+        ];
+        row.extend(cells(self.latency));
+        row.push(cell(self.mean_tip));
+        row.extend(cells(self.tip));
+        row.push(self.total_fees.to_string());
+        row.push(self.overpayment.to_string());
+        row.join(",")
+    }
+}
+
+/// Block-packing model deciding whether a synthetic transaction would have been included.
+///
+/// The builder is assumed to fill the block with its historical transactions ordered by
+/// effective tip, so the synthetic transaction only lands if it pays the base fee and outbids
+/// enough lower-paying gas to make room for itself. Ties go to the historical transactions.
+/// Gas the block used but the dataset has no transaction for is assumed to outbid it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InclusionModel {
+    gas_limit: Option<u64>, // Fill blocks up to this limit instead of the gas they used
+    private_flow: f64,      // Share of the block's gas reserved for private order flow
+}
+
+impl InclusionModel {
+    /// Lets the builder fill blocks up to `gas_limit`, so the room a block left unused is
+    /// available to any transaction paying the base fee.
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// Treats the first transactions of every block, up to `share` of its gas, as private order
+    /// flow the builder includes ahead of the public mempool whatever their tip.
+    pub fn with_private_flow(mut self, share: f64) -> Self {
+        self.private_flow = share.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns `true` if `bid`, using `gas` gas, would have been included in `block`.
+    ///
+    /// Blocks without transaction data cannot tell, so they never include it.
+    pub fn includes(&self, block: &SimulatedBlock, bid: &Bid, gas: u64) -> bool {
+        let Some(tip) = bid.effective_tip(block.base_fee_per_gas) else {
+            return false;
+        };
+        if block.transactions.is_empty() {
+            return false;
+        }
+
+        let mut transactions: Vec<&TransactionData> = block.transactions.iter().collect();
+        transactions.sort_unstable_by_key(|tx| tx.transaction_index);
+
+        let known_gas: u64 = transactions.iter().map(|tx| tx.gas_used).sum();
+        let gas_used = std::cmp::max(block.gas_used, known_gas);
+        let capacity = self.gas_limit.map_or(gas_used, |gas_limit| std::cmp::max(gas_limit, gas_used));
+
+        let private_gas = (self.private_flow * known_gas as f64) as u64;
+        let mut ahead = gas_used - known_gas;
+        let mut private = 0;
+        for tx in transactions {
+            if private + tx.gas_used <= private_gas {
+                private += tx.gas_used;
+                ahead += tx.gas_used;
+            } else if block.tip(tx) >= tip {
+                ahead += tx.gas_used;
+            }
+        }
+
+        ahead.saturating_add(gas) <= capacity
+    }
+
+    /// Returns the lowest tip a transaction using `gas` gas could have paid to be included in
+    /// `block`, in hindsight.
+    pub fn min_tip(&self, block: &SimulatedBlock, gas: u64) -> Option<u128> {
+        // outbidding a transaction takes one more wei than its tip
+        std::iter::once(0)
+            .chain(block.tips().into_iter().map(|tip| tip + 1))
+            .find(|tip| {
+                let bid = Bid { max_fee_per_gas: block.base_fee_per_gas + tip, max_priority_fee_per_gas: *tip };
+                self.includes(block, &bid, gas)
+            })
+    }
+}
+
+/// Replays a [`BlockDataset`], submitting a synthetic transaction every `submit_every` blocks
+/// and pricing it with a [`Strategy`] until it is included or gives up.
+///
+/// Blocks without transaction data cannot tell whether a bid would have been included, so
+/// pending transactions simply wait through them.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    dataset: BlockDataset,
+    start_block: u64,
+    end_block: u64,     // Last block transactions are submitted and included in
+    submit_every: u64,  // Blocks between two synthetic transactions
+    gas_used: u64,      // Gas used by every synthetic transaction
+    inclusion: InclusionModel,
+    strategies: Vec<Arc<dyn Strategy>>, // Compared by `run_all`
+}
+
+impl Simulator {
+    /// Replays the whole dataset, submitting a 21000 gas transaction every block.
+    pub fn new(dataset: BlockDataset) -> Self {
+        let (start_block, end_block) = dataset.range().unwrap_or_default();
+        // the first block has no previous block to price from
+        Self {
+            dataset,
+            start_block: start_block + 1,
+            end_block,
+            submit_every: 1,
+            gas_used: 21_000,
+            inclusion: InclusionModel::default(),
+            strategies: Vec::new(),
+        }
+    }
+
+    /// Registers `strategy` to be replayed by [`Simulator::run_all`].
+    pub fn with_strategy(mut self, strategy: impl Strategy + 'static) -> Self {
+        self.strategies.push(Arc::new(strategy));
+        self
+    }
+
+    /// Restricts the replay to blocks `start_block..=end_block`.
+    pub fn with_range(mut self, start_block: u64, end_block: u64) -> Self {
+        self.start_block = start_block;
+        self.end_block = end_block;
+        self
+    }
+
+    pub fn with_submit_every(mut self, submit_every: u64) -> Self {
+        self.submit_every = std::cmp::max(submit_every, 1);
+        self
+    }
+
+    pub fn with_gas_used(mut self, gas_used: u64) -> Self {
+        self.gas_used = gas_used;
+        self
+    }
+
+    pub fn with_inclusion_model(mut self, inclusion: InclusionModel) -> Self {
+        self.inclusion = inclusion;
+        self
+    }
+
+    pub fn dataset(&self) -> &BlockDataset {
+        &self.dataset
+    }
+
+    /// Replays the dataset with `strategy`.
+    pub fn run(&self, strategy: &dyn Strategy) -> SimulationResult {
+        let mut replay = Replay::new(strategy);
+        for block_number in self.start_block..=self.end_block {
+            replay.step(self, block_number);
+        }
+        replay.finish()
+    }
+
+    /// Replays the dataset once with every registered strategy side by side, each pricing its
+    /// own synthetic transactions over the same blocks and submission schedule, and returns
+    /// their results in registration order. Strategies do not compete with each other.
+    pub fn run_all(&self) -> Vec<SimulationResult> {
+        let mut replays: Vec<Replay> = self.strategies.iter().map(|strategy| Replay::new(strategy.as_ref())).collect();
+        for block_number in self.start_block..=self.end_block {
+            for replay in &mut replays {
+                replay.step(self, block_number);
+            }
+        }
+        replays.into_iter().map(Replay::finish).collect()
+    }
+
+    /// Replays every registered strategy and reports their inclusion delay and cost.
+    pub fn compare(&self) -> Vec<SimulationReport> {
+        self.run_all().iter().map(|result| self.report(result)).collect()
+    }
+
+    /// Summarizes `result`, comparing every included transaction with the lowest tip that would
+    /// have landed it in the same block.
+    pub fn report(&self, result: &SimulationResult) -> SimulationReport {
+        let latencies = result.latencies();
+        let tips: Vec<u128> = result.included().filter_map(|tx| tx.effective_tip).collect();
+        let included = latencies.len();
+
+        let mut latency_histogram = BTreeMap::new();
+        for latency in &latencies {
+            *latency_histogram.entry(*latency).or_default() += 1;
+        }
+
+        let overpayment = result
+            .included()
+            .filter_map(|tx| {
+                let block = self.dataset.block(tx.included_at?)?;
+                let min_tip = self.inclusion.min_tip(block, self.gas_used)?;
+                Some(tx.effective_tip?.saturating_sub(min_tip) * self.gas_used as u128)
+            })
+            .sum();
+
+        let submitted = result.transactions.len();
+        let expired = result.transactions.iter().filter(|tx| tx.expired).count();
+        SimulationReport {
+            strategy: result.strategy.clone(),
+            submitted,
+            included,
+            expired,
+            pending: submitted - included - expired,
+            inclusion_rate: if submitted == 0 { 0.0 } else { included as f64 / submitted as f64 },
+            mean_latency: result.mean_latency(),
+            latency: Percentiles::of(latencies),
+            latency_histogram,
+            mean_tip: (included > 0).then(|| tips.iter().sum::<u128>() / included as u128),
+            tip: Percentiles::of(tips),
+            total_fees: result.total_fees(),
+            overpayment,
+        }
+    }
+}
+
+/// Progress of a single strategy through the replay.
+struct Replay<'a> {
+    strategy: &'a dyn Strategy,
+    pending: Vec<SimulatedTransaction>,
+    done: Vec<SimulatedTransaction>,
+}
+
+impl<'a> Replay<'a> {
+    fn new(strategy: &'a dyn Strategy) -> Self {
+        Self { strategy, pending: Vec::new(), done: Vec::new() }
+    }
+
+    /// Submits the transaction of block `block_number`, if any, and re-prices every pending
+    /// transaction for it.
+    fn step(&mut self, simulator: &Simulator, block_number: u64) {
+        if (block_number - simulator.start_block).is_multiple_of(simulator.submit_every) {
+            self.pending.push(SimulatedTransaction {
+                submitted_at: block_number,
+                included_at: None,
+                expired: false,
+                bid: None,
+                effective_tip: None,
+                fee_paid: 0,
+            });
+        }
+
+        let block = simulator.dataset.block(block_number);
+        let pending = std::mem::take(&mut self.pending);
+        for mut tx in pending {
+            if self.strategy.is_expired(tx.submitted_at, block_number) {
+                tx.expired = true;
+                self.done.push(tx);
+                continue;
+            }
+            // Without a new bid the last one stays in the pool
+            tx.bid = self.strategy.bid(&simulator.dataset, tx.submitted_at, block_number).or(tx.bid);
+            let Some(bid) = tx.bid else {
+                self.pending.push(tx);
+                continue;
+            };
+
+            match block.filter(|block| simulator.inclusion.includes(block, &bid, simulator.gas_used)) {
+                Some(block) => {
+                    let tip = bid.effective_tip(block.base_fee_per_gas).unwrap_or_default();
+                    tx.included_at = Some(block_number);
+                    tx.effective_tip = Some(tip);
+                    tx.fee_paid = (block.base_fee_per_gas + tip) * simulator.gas_used as u128;
+                    self.done.push(tx);
+                }
+                None => self.pending.push(tx),
+            }
+        }
+    }
+
+    fn finish(mut self) -> SimulationResult {
+        self.done.append(&mut self.pending);
+        self.done.sort_by_key(|tx| tx.submitted_at);
+        SimulationResult { strategy: self.strategy.name(), transactions: self.done }
+    }
+}
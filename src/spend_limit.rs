@@ -0,0 +1,47 @@
+use alloy_primitives::utils::{parse_ether, UnitsError};
+
+/// What happens to a bid whose total fee would exceed a [`SpendLimit`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpendLimitAction {
+    /// Fail with [`GasError::SpendLimitExceeded`](crate::GasError).
+    #[default]
+    Refuse,
+    /// Lower the fee cap, and the tip if needed, to the highest the limit allows.
+    Clamp,
+}
+
+/// Cap on the total fee a single transaction may commit to, `gas_limit * max_fee_per_gas`.
+///
+/// Only execution gas is covered; the blob fee of EIP-4844 transactions is capped separately
+/// by [`GasEscalatorFiller::with_max_blob_fee`](crate::GasEscalatorFiller::with_max_blob_fee).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendLimit {
+    max_total_fee: u128, // In wei
+    action: SpendLimitAction,
+}
+
+impl SpendLimit {
+    pub fn new(max_total_fee: u128, action: SpendLimitAction) -> Self {
+        Self { max_total_fee, action }
+    }
+
+    /// Creates a limit from an amount of ether, e.g. `"0.05"`.
+    pub fn from_ether(ether: &str, action: SpendLimitAction) -> Result<Self, UnitsError> {
+        let wei = parse_ether(ether)?;
+        Ok(Self::new(u128::try_from(wei).unwrap_or(u128::MAX), action))
+    }
+
+    pub fn max_total_fee(&self) -> u128 {
+        self.max_total_fee
+    }
+
+    pub fn action(&self) -> SpendLimitAction {
+        self.action
+    }
+
+    /// Returns the highest `max_fee_per_gas` within the limit for a transaction using up to
+    /// `gas_limit` gas.
+    pub fn max_fee_per_gas(&self, gas_limit: u64) -> u128 {
+        self.max_total_fee / std::cmp::max(gas_limit, 1) as u128
+    }
+}
@@ -1,12 +1,986 @@
-use alloy::primitives::{address, U256};
-use alloy_primitives::B256;
-use alloy_provider::{ext::{AnvilApi,TxPoolApi}, Provider, ProviderBuilder, WalletProvider};
-use alloy_rpc_types::TransactionRequest;
-use alloy_network::TransactionBuilder;
-use std::sync::{Arc, Mutex};
-
-use crate::{GasEscalatorFiller, LinearEscalator};
+use alloy::primitives::{address, bytes, U256};
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_primitives::{Address, TxKind, B256};
+use alloy_provider::{
+    ext::AnvilApi,
+    fillers::{GasFillable, TxFiller},
+    utils::Eip1559Estimation,
+    Provider, ProviderBuilder, SendableTx, WalletProvider,
+};
+use alloy_rpc_types::{TransactionReceipt, TransactionRequest, TransactionTrait};
+use alloy_transport::{RpcError, TransportResult};
+use alloy_network::{Ethereum, TransactionBuilder, TransactionBuilder4844};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+
+use crate::{
+    max_base_fee_after, AggregateOracle, Aggregation, BudgetAction, Clock, Escalator, EscalatorFillable, EscalatorProviderExt, ExpiryPolicy, FeeEstimate, FeeHistoryEstimator, GasBudget, GasError, GasOracle, MempoolTipEstimator, GasEscalatorFiller, GeometricEscalator, LinearEscalator, PendingContext,
+    PendingDetection, PooledTransaction, ReplacementPolicy, SpendLimit, SpendLimitAction, StaticOracle, SubPool, TimeEscalator, Urgency,
+};
 use crate::gas_anvil::GasAnvil;
+use crate::mock_node::MockNode;
+use crate::rebroadcast::{settle, Outcome};
+use crate::txpool::TxPoolLookup;
+
+const SENDER: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+/// 1 gwei escalating by 0.1 gwei per block, up to 10 gwei over 10 blocks.
+fn escalator() -> LinearEscalator {
+    LinearEscalator::new(1_000_000_000, 100_000_000, 10_000_000_000, 0, 10)
+}
+
+/// First send of `SENDER` with `nonce` at `current_block`.
+fn ctx(nonce: u64, current_block: u64) -> PendingContext {
+    PendingContext { from: SENDER, nonce, chain_id: None, current_block, mined_nonce: 0, pending_tx: None }
+}
+
+#[test]
+fn test_linear_escalator_bid() {
+    let mut escalator = LinearEscalator::new(
+        1_000_000_000,
+        100_000_000,
+        1_500_000_000,
+        10,
+        10,
+    );
+
+    assert_eq!(escalator.bid(10), 1_000_000_000);
+    assert_eq!(escalator.bid(12), 1_200_000_000);
+    assert_eq!(escalator.bid(19), 1_500_000_000, "bid should be capped at max_bid");
+    assert!(escalator.is_expired(20));
+    assert_eq!(escalator.bid(20), 0);
+
+    escalator.reset(20);
+    assert!(!escalator.is_expired(20));
+    assert_eq!(escalator.bid(21), 1_100_000_000);
+}
+
+#[test]
+fn test_geometric_escalator_bid() {
+    let escalator = GeometricEscalator::new(1_000_000_000, 1250, 1_500_000_000, 0, 10);
+
+    assert_eq!(escalator.bid(0), 1_000_000_000);
+    assert_eq!(escalator.bid(1), 1_125_000_000);
+    assert_eq!(escalator.bid(2), 1_265_625_000);
+    assert_eq!(escalator.bid(9), 1_500_000_000, "bid should be capped at max_bid");
+    assert_eq!(escalator.bid(10), 0);
+}
+
+#[derive(Clone, Debug, Default)]
+struct MockClock(Arc<AtomicU64>);
+
+impl MockClock {
+    fn advance(&self, secs: u64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn test_time_escalator_bid() {
+    let clock = MockClock::default();
+    clock.advance(1_000);
+    let mut escalator = TimeEscalator::with_clock(1_000_000_000, 10_000_000, 1_500_000_000, 120, clock.clone());
+
+    assert_eq!(escalator.bid(0), 1_000_000_000);
+    clock.advance(12);
+    assert_eq!(escalator.bid(0), 1_120_000_000, "block number should not affect the bid");
+    assert_eq!(escalator.bid(100), 1_120_000_000);
+    clock.advance(60);
+    assert_eq!(escalator.bid(1), 1_500_000_000, "bid should be capped at max_bid");
+    clock.advance(48);
+    assert!(escalator.is_expired(1));
+    assert_eq!(escalator.bid(1), 0);
+
+    escalator.reset(2);
+    assert!(!escalator.is_expired(2));
+    assert_eq!(escalator.bid(2), 1_000_000_000);
+}
+
+#[test]
+fn test_time_escalator_block_time() {
+    let clock = MockClock::default();
+    let mut escalator = TimeEscalator::with_clock(0, 0, 0, 0, clock.clone()).with_block_time(2);
+    let preset = Urgency::Fast.preset();
+
+    // +20% per 2 second block, for 10 blocks
+    escalator.apply_urgency(&preset, 1_000_000_000, 10_000_000_000);
+    clock.advance(4);
+    assert_eq!(escalator.bid(0), 1_400_000_000);
+    assert_eq!(escalator.remaining_blocks(0), 8);
+    clock.advance(15);
+    assert!(!escalator.is_expired(0));
+    clock.advance(1);
+    assert!(escalator.is_expired(0), "preset should last 10 blocks of 2 seconds");
+}
+
+#[test]
+fn test_per_transaction_escalation_state() {
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+
+    assert_eq!(filler.next_bid(SENDER, 0, 100, false), 1_000_000_000);
+    assert_eq!(filler.next_bid(SENDER, 0, 102, true), 1_200_000_000);
+    // a second in-flight transaction starts its own escalation
+    assert_eq!(filler.next_bid(SENDER, 1, 103, false), 1_000_000_000);
+    assert_eq!(filler.next_bid(SENDER, 0, 103, true), 1_300_000_000);
+
+    let state = filler.state(SENDER, 0).unwrap();
+    assert_eq!(state.start_block, 100);
+    assert_eq!(state.current_bid, 1_300_000_000);
+    assert_eq!(state.attempts, 3);
+
+    filler.clear_mined(SENDER, 1);
+    assert!(filler.state(SENDER, 0).is_none());
+    assert_eq!(filler.state(SENDER, 1).unwrap().attempts, 1);
+}
+
+#[test]
+fn test_fee_history_start_bid() {
+    let rewards = vec![
+        vec![2_000_000_000],
+        vec![0], // empty block
+        vec![1_000_000_000],
+        vec![3_000_000_000],
+    ];
+    assert_eq!(FeeHistoryEstimator::tip_from_rewards(&rewards), Some(2_000_000_000));
+    assert_eq!(FeeHistoryEstimator::tip_from_rewards(&[vec![0], vec![]]), None);
+
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    let ctx = ctx(0, 100);
+
+    assert_eq!(filler.start_escalation(&ctx, |escalator| escalator.set_start_bid(2_000_000_000)), 2_000_000_000);
+    assert_eq!(filler.next_bid(SENDER, 0, 102, true), 2_200_000_000, "escalation should continue from the estimate");
+    // the template is untouched
+    assert_eq!(filler.escalator().bid(0), 1_000_000_000);
+}
+
+/// Escalator implementing only the required methods: a fixed bid for 10 blocks.
+#[derive(Clone, Debug)]
+struct FixedEscalator {
+    start_block: u64,
+}
+
+impl Escalator for FixedEscalator {
+    fn bid(&self, block: u64) -> u128 {
+        if self.is_expired(block) { 0 } else { 1_000_000_000 }
+    }
+
+    fn is_expired(&self, block: u64) -> bool {
+        block >= self.start_block + 10
+    }
+
+    fn remaining_blocks(&self, block: u64) -> u64 {
+        (self.start_block + 10).saturating_sub(block)
+    }
+
+    fn max_bid(&self) -> u128 {
+        1_000_000_000
+    }
+
+    fn reset(&mut self, start_block: u64) {
+        self.start_block = start_block;
+    }
+}
+
+#[test]
+fn test_escalator_defaults_ignore_market() {
+    let filler = GasEscalatorFiller::with_escalator(FixedEscalator { start_block: 0 });
+    let preset = Urgency::Fast.preset();
+
+    assert_eq!(filler.start_escalation(&ctx(0, 100), |escalator| escalator.set_start_bid(2_000_000_000)), 1_000_000_000);
+    let bid = filler.start_escalation(&ctx(1, 100), |escalator| {
+        escalator.apply_urgency(&preset, 2_000_000_000, preset.max_bid(10_000_000_000))
+    });
+    assert_eq!(bid, 1_000_000_000, "urgency presets should not apply by default");
+}
+
+#[test]
+fn test_urgency_presets() {
+    let filler = GasEscalatorFiller::with_escalator(LinearEscalator::default()).with_urgency(Urgency::Slow);
+    filler.set_urgency(SENDER, 1, Urgency::Fast);
+
+    assert_eq!(filler.urgency(SENDER, 0), Some(Urgency::Slow), "filler urgency should be the default");
+    assert_eq!(filler.urgency(SENDER, 1), Some(Urgency::Fast));
+
+    let preset = Urgency::Fast.preset();
+    let ctx = ctx(1, 100);
+    // 1 gwei market tip, 10 gwei base fee
+    let bid = filler.start_escalation(&ctx, |escalator| {
+        escalator.apply_urgency(&preset, 1_000_000_000, preset.max_bid(10_000_000_000))
+    });
+    assert_eq!(bid, 1_000_000_000);
+    assert_eq!(filler.next_bid(SENDER, 1, 101, true), 1_200_000_000, "fast should bump 20% per block");
+
+    let escalator = filler.state(SENDER, 1).unwrap().escalator;
+    assert_eq!(escalator.max_bid(), 30_000_000_000, "fast should cap at 3x the base fee");
+    assert!(escalator.is_expired(110), "fast should expire after 10 blocks");
+
+    filler.clear_mined(SENDER, 2);
+    assert_eq!(filler.urgency(SENDER, 1), Some(Urgency::Slow));
+}
+
+#[test]
+fn test_base_fee_headroom() {
+    let params = BaseFeeParams::ethereum();
+    assert_eq!(max_base_fee_after(8_000_000_000, 0, params), 8_000_000_000);
+    assert_eq!(max_base_fee_after(8_000_000_000, 1, params), 9_000_000_000, "a full block should add 12.5%");
+    assert_eq!(max_base_fee_after(8_000_000_000, 2, params), 10_125_000_000);
+    assert_eq!(max_base_fee_after(7, 2, params), 9, "growth should be at least 1 wei per block");
+
+    // OP-stack chains grow by (6 - 1) / 50 = 10% per full block
+    let optimism = BaseFeeParams::optimism();
+    assert_eq!(max_base_fee_after(10_000_000_000, 1, optimism), 11_000_000_000, "a full OP block should add 10%");
+    assert_eq!(max_base_fee_after(10_000_000_000, 2, optimism), 12_100_000_000);
+
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_max_headroom_blocks(5);
+
+    assert_eq!(filler.max_base_fee(&ctx(0, 100), 8_000_000_000), 8_000_000_000, "untracked transactions get no headroom");
+
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.max_base_fee(&ctx(0, 100), 8_000_000_000), max_base_fee_after(8_000_000_000, 5, params));
+    assert_eq!(filler.max_base_fee(&ctx(0, 107), 8_000_000_000), 10_125_000_000, "headroom should shrink with the escalation");
+    assert_eq!(filler.max_base_fee(&ctx(0, 110), 8_000_000_000), 8_000_000_000);
+
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(
+        filler.max_base_fee(&ctx(0, 100), 8_000_000_000),
+        max_base_fee_after(8_000_000_000, 6, params),
+        "headroom should be capped at 6 blocks by default"
+    );
+
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_max_headroom_blocks(u64::MAX);
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.max_base_fee(&ctx(0, 100), 8_000_000_000), max_base_fee_after(8_000_000_000, 9, params));
+
+    // growth saturates instead of overflowing, and stops there
+    assert_eq!(max_base_fee_after(10_000_000_000, 600, params), u128::MAX);
+    assert_eq!(max_base_fee_after(10_000_000_000, u64::MAX, params), u128::MAX);
+}
+
+#[tokio::test]
+async fn test_uncapped_headroom_saturates_fee_cap() {
+    let filler = GasEscalatorFiller::with_escalator(LinearEscalator::new(1_000_000_000, 100_000_000, 10_000_000_000, 0, 1_000))
+        .with_oracle(StaticOracle::new(10_000_000_000, 1_000_000_000))
+        .with_pending_detection(PendingDetection::TxPool)
+        .with_max_headroom_blocks(u64::MAX);
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 20_000_000_000, 1_000_000_000) },
+        "queued": {},
+    }));
+    let provider = node.provider();
+    filler.next_bid(SENDER, 0, 1, false);
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_nonce(0)
+        .with_gas_limit(21_000)
+        .with_max_fee_per_gas(20_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000);
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+    let GasFillable::Eip1559 { estimate, .. } = fillable.gas else { panic!("expected EIP-1559 fees") };
+    assert_eq!(estimate.max_fee_per_gas, u128::MAX, "a saturated headroom should not wrap the fee cap");
+}
+
+#[test]
+fn test_mempool_tip_for_gas() {
+    let tips = vec![
+        (1_000_000_000, 5_000_000),
+        (3_000_000_000, 5_000_000),
+        (2_000_000_000, 5_000_000),
+        (500_000_000, 5_000_000),
+    ];
+
+    assert_eq!(MempoolTipEstimator::tip_for_gas(tips.clone(), 10_000_000), Some(2_000_000_001));
+    assert_eq!(MempoolTipEstimator::tip_for_gas(tips.clone(), 12_000_000), Some(1_000_000_001));
+    assert_eq!(MempoolTipEstimator::tip_for_gas(tips, 30_000_000), None, "a mempool below the target gas is not competitive");
+
+    let filler = GasEscalatorFiller::with_escalator(LinearEscalator::new(
+        1_000_000_000,
+        100_000_000,
+        3_000_000_000,
+        0,
+        10,
+    ));
+    let ctx = ctx(0, 100);
+
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.raise_bid(&ctx, 500_000_000), 1_000_000_000, "a lower tip should not lower the bid");
+    assert_eq!(filler.raise_bid(&ctx, 2_000_000_001), 2_000_000_001);
+    assert_eq!(filler.raise_bid(&ctx, 5_000_000_000), 3_000_000_000, "the max bid should cap the tip");
+}
+
+#[test]
+fn test_spend_limit() {
+    let limit = SpendLimit::from_ether("0.001", SpendLimitAction::Refuse).unwrap();
+    assert_eq!(limit.max_total_fee(), 1_000_000_000_000_000);
+    assert_eq!(limit.max_fee_per_gas(100_000), 10_000_000_000);
+
+    let filler = GasEscalatorFiller::default().with_spend_limit(limit);
+    filler.set_spend_limit(SENDER, 1, SpendLimit::new(limit.max_total_fee(), SpendLimitAction::Clamp));
+    let estimate = |max_fee_per_gas, max_priority_fee_per_gas| Eip1559Estimation {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    };
+
+    assert_eq!(
+        filler.limit_spend(&ctx(0, 0), 100_000, estimate(9_000_000_000, 1_000_000_000), 0, 0).unwrap(),
+        estimate(9_000_000_000, 1_000_000_000)
+    );
+    assert!(matches!(
+        filler.limit_spend(&ctx(0, 0), 100_000, estimate(12_000_000_000, 1_000_000_000), 0, 0),
+        Err(GasError::SpendLimitExceeded { required: 1_200_000_000_000_000, .. })
+    ));
+
+    // the per-transaction limit clamps instead
+    assert_eq!(
+        filler.limit_spend(&ctx(1, 0), 100_000, estimate(12_000_000_000, 11_000_000_000), 0, 0).unwrap(),
+        estimate(10_000_000_000, 10_000_000_000)
+    );
+    assert!(
+        matches!(
+            filler.limit_spend(&ctx(1, 0), 100_000, estimate(12_000_000_000, 1_000_000_000), 11_000_000_000, 0),
+            Err(GasError::ReplacementUnderpriced { cap: 10_000_000_000, .. })
+        ),
+        "clamping below the replacement fee should be refused"
+    );
+}
+
+#[test]
+fn test_gas_budget_windows() {
+    let clock = MockClock::default();
+    let budget = GasBudget::with_clock(BudgetAction::Block, clock.clone())
+        .with_window(Duration::from_secs(3_600), 1_000)
+        .with_window(Duration::from_secs(86_400), 2_500);
+    let other = address!("000000000000000000000000000000000000dEaD");
+
+    assert_eq!(budget.remaining(SENDER), 1_000);
+    budget.record_spend(SENDER, 600);
+    assert_eq!(budget.remaining(SENDER), 400);
+    assert_eq!(budget.remaining(other), 1_000, "budgets should be tracked per account");
+
+    clock.advance(3_600);
+    assert_eq!(budget.remaining(SENDER), 1_000, "the hourly window should roll over");
+    budget.record_spend(SENDER, 900);
+    clock.advance(3_600);
+    budget.record_spend(SENDER, 900);
+    assert_eq!(budget.remaining(SENDER), 100, "the daily window should cap the remaining budget");
+
+    clock.advance(86_400);
+    assert_eq!(budget.remaining(SENDER), 1_000);
+}
+
+#[test]
+fn test_gas_budget_limits_fill() {
+    let budget = GasBudget::new(BudgetAction::Block).with_window(Duration::from_secs(3_600), 1_000_000_000_000_000);
+    let filler = GasEscalatorFiller::default().with_budget(budget.clone());
+    let ctx = ctx(0, 0);
+    let estimate = Eip1559Estimation { max_fee_per_gas: 5_000_000_000, max_priority_fee_per_gas: 1_000_000_000 };
+
+    assert_eq!(filler.limit_spend(&ctx, 100_000, estimate, 0, 0).unwrap(), estimate);
+
+    budget.record_spend(SENDER, 800_000_000_000_000);
+    assert!(matches!(
+        filler.limit_spend(&ctx, 100_000, estimate, 0, 0),
+        Err(GasError::BudgetExhausted { required: 500_000_000_000_000, remaining: 200_000_000_000_000, .. })
+    ));
+
+    let filler = GasEscalatorFiller::default().with_budget(
+        GasBudget::new(BudgetAction::Downgrade).with_window(Duration::from_secs(3_600), 200_000_000_000_000),
+    );
+    assert_eq!(
+        filler.limit_spend(&ctx, 100_000, estimate, 0, 0).unwrap(),
+        Eip1559Estimation { max_fee_per_gas: 2_000_000_000, max_priority_fee_per_gas: 1_000_000_000 },
+        "a downgraded escalation should fit the remaining budget"
+    );
+    assert!(
+        matches!(
+            filler.limit_spend(&ctx, 100_000, estimate, 0, 3_000_000_000),
+            Err(GasError::BudgetExhausted { remaining: 200_000_000_000_000, .. })
+        ),
+        "a downgrade below the base fee should be refused"
+    );
+}
+
+#[test]
+fn test_gas_budget_reserves_in_flight_fees() {
+    let clock = MockClock::default();
+    let budget = GasBudget::with_clock(BudgetAction::Block, clock).with_window(Duration::from_secs(3_600), 1_000_000_000_000_000);
+    let filler = GasEscalatorFiller::default().with_budget(budget.clone());
+    let estimate = Eip1559Estimation { max_fee_per_gas: 5_000_000_000, max_priority_fee_per_gas: 1_000_000_000 };
+
+    assert!(filler.limit_spend(&ctx(0, 0), 100_000, estimate, 0, 0).is_ok());
+    assert_eq!(budget.remaining(SENDER), 500_000_000_000_000, "fees in flight should be reserved");
+    assert!(
+        matches!(
+            filler.limit_spend(&ctx(1, 0), 120_000, estimate, 0, 0),
+            Err(GasError::BudgetExhausted { remaining: 500_000_000_000_000, .. })
+        ),
+        "a concurrent transaction should not spend the reserved fees"
+    );
+    assert!(filler.limit_spend(&ctx(0, 0), 120_000, estimate, 0, 0).is_ok(), "a replacement reuses its own reservation");
+    assert_eq!(budget.remaining(SENDER), 400_000_000_000_000);
+
+    filler.clear_mined(SENDER, 1);
+    assert_eq!(budget.remaining(SENDER), 1_000_000_000_000_000, "mined nonces should release their reservation");
+
+    // a recorded receipt replaces the reservation of its nonce with the fee actually paid
+    let receipt: TransactionReceipt = serde_json::from_value(receipt_json(B256::with_last_byte(1), 1_000_000_000)).unwrap();
+    assert!(filler.limit_spend(&ctx(1, 0), 100_000, estimate, 0, 0).is_ok());
+    filler.record_receipt(1, &receipt);
+    assert_eq!(budget.remaining(SENDER), 1_000_000_000_000_000 - 21_000_000_000_000);
+
+    budget.reserve(SENDER, 2, 500_000_000_000_000);
+    budget.record_receipt(2, &receipt);
+    assert_eq!(budget.remaining(SENDER), 1_000_000_000_000_000 - 42_000_000_000_000);
+}
+
+#[derive(Debug)]
+struct FailingOracle;
+
+#[async_trait::async_trait]
+impl GasOracle for FailingOracle {
+    async fn estimate(&self, _percentile: f64) -> TransportResult<FeeEstimate> {
+        Err(RpcError::NullResp)
+    }
+}
+
+#[tokio::test]
+async fn test_aggregate_oracle() {
+    let oracles = |aggregation| {
+        AggregateOracle::new(aggregation)
+            .with_oracle(StaticOracle::new(10_000_000_000, 1_000_000_000))
+            .with_oracle(StaticOracle::new(11_000_000_000, 2_000_000_000))
+            .with_weighted_oracle(StaticOracle::new(50_000_000_000, 40_000_000_000), 2)
+            .with_oracle(FailingOracle)
+    };
+
+    let median = oracles(Aggregation::Median).estimate(20.0).await.unwrap();
+    assert_eq!(median, FeeEstimate { base_fee: 11_000_000_000, priority_fee: Some(2_000_000_000) });
+
+    let max = oracles(Aggregation::Max).estimate(20.0).await.unwrap();
+    assert_eq!(max, FeeEstimate { base_fee: 50_000_000_000, priority_fee: Some(40_000_000_000) });
+
+    let weighted = oracles(Aggregation::Weighted).estimate(20.0).await.unwrap();
+    assert_eq!(weighted, FeeEstimate { base_fee: 30_250_000_000, priority_fee: Some(20_750_000_000) });
+
+    let failing = AggregateOracle::default().with_oracle(FailingOracle);
+    assert!(failing.estimate(20.0).await.is_err(), "estimate should fail when every source does");
+}
+
+#[tokio::test]
+async fn test_oracle_base_fee_bounds_fee_cap() {
+    let filler = GasEscalatorFiller::with_escalator(escalator())
+        .with_oracle(StaticOracle::new(50_000_000_000, 1_000_000_000))
+        .with_pending_detection(PendingDetection::TxPool)
+        .with_max_headroom_blocks(0);
+    // no eth_feeHistory nor block answers: the node's base fee is never asked for
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 2_000_000_000, 1_000_000_000) },
+        "queued": {},
+    }));
+    let provider = node.provider();
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_max_fee_per_gas(2_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000)
+        .with_gas_limit(21_000)
+        .with_nonce(0);
+    filler.next_bid(SENDER, 0, 0, false);
+
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+    let estimate = Eip1559Estimation { max_fee_per_gas: 51_100_000_000, max_priority_fee_per_gas: 1_100_000_000 };
+    assert_eq!(fillable.gas, GasFillable::Eip1559 { gas_limit: 21_000, estimate });
+}
+
+#[test]
+fn test_expiry_policy() {
+    let escalator = escalator();
+    let filler = GasEscalatorFiller::with_escalator(escalator.clone());
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.resend_bid(&ctx(0, 105)).unwrap(), 1_500_000_000);
+    assert!(matches!(filler.resend_bid(&ctx(0, 110)), Err(GasError::EscalationExpired { nonce: 0, .. })));
+
+    let filler = GasEscalatorFiller::with_escalator(escalator.clone()).with_expiry_policy(ExpiryPolicy::MaxBid);
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.resend_bid(&ctx(0, 110)).unwrap(), 10_000_000_000, "expired escalation should bid the max");
+    assert!(!filler.state(SENDER, 0).unwrap().cancelled);
+
+    let filler = GasEscalatorFiller::with_escalator(escalator).with_expiry_policy(ExpiryPolicy::Cancel);
+    filler.next_bid(SENDER, 0, 100, false);
+    assert_eq!(filler.resend_bid(&ctx(0, 110)).unwrap(), 10_000_000_000);
+    assert!(filler.state(SENDER, 0).unwrap().cancelled, "expired escalation should be cancelled");
+}
+
+#[tokio::test]
+async fn test_cancellation_fill() {
+    let filler = GasEscalatorFiller::default();
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_value(U256::from(125))
+        .with_input(bytes!("deadbeef"))
+        .with_nonce(0);
+    let fillable = EscalatorFillable {
+        gas: GasFillable::Legacy { gas_limit: 50_000, gas_price: 2_000_000_000 },
+        max_fee_per_blob_gas: None,
+        cancel: true,
+    };
+
+    let tx = TxFiller::<Ethereum>::fill(&filler, fillable, SendableTx::Builder(tx)).await.unwrap();
+    let tx = tx.as_builder().unwrap();
+    assert_eq!(tx.to, Some(TxKind::Call(SENDER)), "cancellation should be a self-transfer");
+    assert_eq!(tx.value, Some(U256::ZERO));
+    assert!(tx.input.input().unwrap().is_empty());
+    assert_eq!(tx.gas, Some(21_000));
+    assert_eq!(tx.gas_price, Some(2_000_000_000));
+}
+
+#[tokio::test]
+async fn test_default_filler_resends() {
+    let filler = GasEscalatorFiller::default();
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({ "pending": {}, "queued": {} }));
+    let provider = node.provider();
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_gas_price(2_000_000_000)
+        .with_gas_limit(21_000)
+        .with_nonce(0);
+    TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 2_000_000_000, 2_000_000_000) },
+        "queued": {},
+    }));
+    node.respond("eth_blockNumber", serde_json::json!("0x3"));
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+    assert_eq!(
+        fillable.gas,
+        GasFillable::Legacy { gas_limit: 21_000, gas_price: 3_200_000_000 },
+        "the default escalator should keep bidding"
+    );
+
+    // a market start bid is not discarded by an already expired escalation
+    let filler = GasEscalatorFiller::default();
+    assert_eq!(filler.start_escalation(&ctx(1, 100), |escalator| escalator.set_start_bid(2_000_000_000)), 2_000_000_000);
+}
+
+#[tokio::test]
+async fn test_cancellation_skips_expiry() {
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_pending_detection(PendingDetection::TxPool);
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 2_000_000_000, 2_000_000_000) },
+        "queued": {},
+    }));
+    let provider = node.provider();
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_value(U256::from(125))
+        .with_gas_price(2_000_000_000)
+        .with_gas_limit(21_000)
+        .with_nonce(0);
+    let cancellation = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(SENDER)
+        .with_value(U256::ZERO)
+        .with_gas_price(2_000_000_000)
+        .with_gas_limit(21_000)
+        .with_nonce(0);
+    filler.next_bid(SENDER, 0, 0, false);
+
+    // past the escalator's valid length, the original is refused
+    node.respond("eth_blockNumber", serde_json::json!("0x14"));
+    let err = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap_err();
+    assert!(matches!(GasError::from_transport_error(&err), Some(GasError::EscalationExpired { nonce: 0, .. })));
+
+    // but its cancellation still goes out, at the max bid
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &cancellation).await.unwrap();
+    assert_eq!(fillable.gas, GasFillable::Legacy { gas_limit: 21_000, gas_price: 12_000_000_000 });
+
+    // and further sends of the original stand down
+    let err = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap_err();
+    assert!(matches!(GasError::from_transport_error(&err), Some(GasError::TransactionCancelled { nonce: 0, .. })));
+}
+
+#[tokio::test]
+async fn test_blob_fee_replacement_bump() {
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_max_blob_fee(5_000);
+    // never queried: the requested blob fee is used as the estimate
+    let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap());
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_nonce(0)
+        .with_max_fee_per_blob_gas(1_000);
+
+    filler.next_bid(SENDER, 0, 0, false);
+    assert_eq!(filler.prepare_blob_fee(&provider, &tx).await.unwrap(), 1_000);
+    assert_eq!(filler.prepare_blob_fee(&provider, &tx).await.unwrap(), 2_000, "replacement should double the blob fee");
+    assert_eq!(filler.prepare_blob_fee(&provider, &tx).await.unwrap(), 4_000);
+    let err = filler.prepare_blob_fee(&provider, &tx).await.unwrap_err();
+    assert!(
+        matches!(
+            GasError::from_transport_error(&err),
+            Some(GasError::ReplacementUnderpriced { required: 8_000, cap: 5_000, .. })
+        ),
+        "replacement above the blob fee cap should be refused"
+    );
+    assert_eq!(filler.state(SENDER, 0).unwrap().blob_fee, Some(4_000), "a refused blob fee is not recorded");
+    assert!(filler.prepare_blob_fee(&provider, &tx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_blob_replacement_doubles_every_fee() {
+    let filler = GasEscalatorFiller::with_escalator(escalator())
+        .with_oracle(StaticOracle::new(10_000_000_000, 1_000_000_000))
+        .with_pending_detection(PendingDetection::TxPool);
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({ "pending": {}, "queued": {} }));
+    let provider = node.provider();
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_nonce(0)
+        .with_gas_limit(21_000)
+        .with_max_fee_per_gas(20_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000)
+        .with_max_fee_per_blob_gas(1_000)
+        .with_blob_sidecar(Default::default());
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+    assert_eq!(fillable.max_fee_per_blob_gas, Some(1_000));
+
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 20_000_000_000, 1_000_000_000) },
+        "queued": {},
+    }));
+    node.respond("eth_blockNumber", serde_json::json!("0x2"));
+    let fillable = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap();
+    let GasFillable::Eip1559 { estimate, .. } = fillable.gas else { panic!("expected EIP-1559 fees") };
+    assert_eq!(estimate.max_fee_per_gas, 40_000_000_000, "a blob replacement should double the fee cap");
+    assert_eq!(estimate.max_priority_fee_per_gas, 2_000_000_000, "and the tip");
+    assert_eq!(fillable.max_fee_per_blob_gas, Some(2_000), "and the blob fee");
+}
+
+#[test]
+fn test_replacement_policy() {
+    let policy = ReplacementPolicy::default().with_chain_bump(137, 30);
+
+    assert_eq!(policy.min_replacement_fee(1_000_000_000, Some(1)), 1_100_000_000);
+    assert_eq!(policy.min_replacement_fee(1_000_000_000, None), 1_100_000_000);
+    assert_eq!(policy.min_replacement_fee(1_000_000_000, Some(137)), 1_300_000_000);
+    assert_eq!(policy.min_replacement_fee(1_000_000_001, Some(1)), 1_100_000_002, "bump should round up");
+    assert_eq!(policy.min_replacement_fee(1, Some(1)), 2, "replacement should strictly increase the fee");
+}
+
+#[test]
+fn test_min_replacement_fees_from_pooled_transaction() {
+    let filler = GasEscalatorFiller::default();
+    let mut ctx = PendingContext {
+        chain_id: Some(1),
+        pending_tx: Some(PooledTransaction {
+            tx_hash: None,
+            pool: SubPool::Pending,
+            max_fee_per_gas: Some(30_000_000_000),
+            max_priority_fee_per_gas: Some(2_000_000_000),
+        }),
+        ..ctx(0, 0)
+    };
+    let estimate = (20_000_000_000, 1_000_000_000);
+
+    assert_eq!(filler.min_replacement_fees(&ctx, estimate, false), (33_000_000_000, 2_200_000_000));
+
+    // unknown pooled fees fall back to the estimate
+    ctx.pending_tx.as_mut().unwrap().max_fee_per_gas = None;
+    assert_eq!(filler.min_replacement_fees(&ctx, estimate, false), (22_000_000_000, 1_100_000_000));
+}
+
+#[test]
+fn test_replacement_above_max_bid() {
+    let filler = GasEscalatorFiller::with_escalator(LinearEscalator::new(1_000_000_000, 100_000_000, 2_000_000_000, 0, 10));
+    let ctx = ctx(3, 0);
+
+    assert!(filler.check_max_bid(&ctx, 2_000_000_000).is_ok());
+
+    let err = filler.check_max_bid(&ctx, 2_200_000_000).unwrap_err();
+    assert!(matches!(
+        err,
+        GasError::MaxBidReached { nonce: 3, required: 2_200_000_000, max_bid: 2_000_000_000, .. }
+    ));
+    assert_eq!(
+        err.to_string(),
+        format!("replacing transaction {SENDER}/3 requires a bid of 2200000000 wei, above the max bid of 2000000000 wei")
+    );
+}
+
+#[tokio::test]
+async fn test_replacement_above_max_bid_refused() {
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_pending_detection(PendingDetection::TxPool);
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 20_000_000_000, 20_000_000_000) },
+        "queued": {},
+    }));
+    let provider = node.provider();
+    filler.next_bid(SENDER, 0, 1, false);
+
+    // replacing the pooled 20 gwei transaction at a 10 gwei gas price takes a 12 gwei bid,
+    // above the 10 gwei max bid
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_nonce(0)
+        .with_gas_limit(21_000)
+        .with_gas_price(10_000_000_000);
+    let err = TxFiller::<Ethereum>::prepare(&filler, &provider, &tx).await.unwrap_err();
+    assert!(matches!(
+        GasError::from_transport_error(&err),
+        Some(GasError::MaxBidReached { nonce: 0, required: 12_000_000_000, max_bid: 10_000_000_000, .. })
+    ));
+}
+
+#[test]
+fn test_rebroadcast_settles_on_gas_errors() {
+
+    assert!(settle::<()>(SENDER, 3, Ok(Outcome::Pending)).is_none());
+    assert!(matches!(settle(SENDER, 3, Ok(Outcome::Mined(7))), Some(Ok(7))));
+
+    // transport errors are retried
+    let transient = RpcError::Transport(alloy_transport::TransportErrorKind::BackendGone);
+    assert!(settle::<()>(SENDER, 3, Err(transient)).is_none());
+
+    // errors raised by the filler end the rebroadcast
+    let expired = GasError::EscalationExpired { from: SENDER, nonce: 3 }.into();
+    let result = settle::<()>(SENDER, 3, Err(expired)).unwrap().unwrap_err();
+    assert!(matches!(GasError::from_transport_error(&result), Some(GasError::EscalationExpired { nonce: 3, .. })));
+
+    let result = settle::<()>(SENDER, 3, Ok(Outcome::NonceConsumed)).unwrap().unwrap_err();
+    assert!(matches!(GasError::from_transport_error(&result), Some(GasError::NonceConsumed { nonce: 3, .. })));
+}
+
+/// A pending EIP-1559 transaction as returned by the `txpool_` namespace.
+fn pooled_tx_json(from: Address, nonce: u64, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> serde_json::Value {
+    serde_json::json!({
+        "type": "0x2",
+        "chainId": "0x1",
+        "hash": B256::with_last_byte(nonce as u8),
+        "nonce": format!("{nonce:#x}"),
+        "from": from,
+        "to": from,
+        "value": "0x0",
+        "gas": "0x5208",
+        "maxFeePerGas": format!("{max_fee_per_gas:#x}"),
+        "maxPriorityFeePerGas": format!("{max_priority_fee_per_gas:#x}"),
+        "input": "0x",
+        "accessList": [],
+        "r": "0x1",
+        "s": "0x1",
+        "yParity": "0x0",
+        "v": "0x0",
+        "blockHash": null,
+        "blockNumber": null,
+        "transactionIndex": null,
+    })
+}
+
+#[tokio::test]
+async fn test_txpool_content_from_fallback() {
+    let node = MockNode::new();
+    node.reject("txpool_contentFrom");
+    node.respond("txpool_content", serde_json::json!({
+        "pending": { SENDER.to_string(): { "3": pooled_tx_json(SENDER, 3, 20_000_000_000, 1_000_000_000) } },
+        "queued": {},
+    }));
+    let provider = node.provider();
+    let lookup = TxPoolLookup::new(PendingDetection::TxPool);
+
+    let pooled = lookup.find(&provider, SENDER, 3, None, 10).await.unwrap().unwrap();
+    assert_eq!(pooled.pool, SubPool::Pending);
+    assert_eq!(pooled.max_fee_per_gas, Some(20_000_000_000));
+    assert_eq!(pooled.max_priority_fee_per_gas, Some(1_000_000_000));
+    assert!(lookup.find(&provider, SENDER, 4, None, 11).await.unwrap().is_none());
+
+    // txpool_contentFrom is not retried once rejected
+    assert_eq!(node.calls("txpool_contentFrom"), 1);
+    assert_eq!(node.calls("txpool_content"), 2);
+}
+
+#[tokio::test]
+async fn test_txpool_cached_per_block() {
+    let node = MockNode::new();
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "3": pooled_tx_json(SENDER, 3, 20_000_000_000, 1_000_000_000) },
+        "queued": {},
+    }));
+    let provider = node.provider();
+    let lookup = TxPoolLookup::new(PendingDetection::TxPool);
+
+    assert!(lookup.find(&provider, SENDER, 3, None, 10).await.unwrap().is_some());
+    assert!(lookup.find(&provider, SENDER, 4, None, 10).await.unwrap().is_none());
+    assert_eq!(node.calls("txpool_contentFrom"), 1, "same block is served from the cache");
+
+    // a broadcast the node may have rejected is not trusted
+    lookup.note_sent(SENDER, 3, 3, 10, 30_000_000_000, 2_000_000_000);
+    let pooled = lookup.find(&provider, SENDER, 3, None, 10).await.unwrap().unwrap();
+    assert_eq!(pooled.max_fee_per_gas, Some(20_000_000_000));
+    assert_eq!(node.calls("txpool_contentFrom"), 2);
+
+    // once accepted, it is
+    let tx_hash = B256::with_last_byte(0xaa);
+    lookup.note_sent(SENDER, 3, 3, 10, 30_000_000_000, 2_000_000_000);
+    lookup.confirm_sent(SENDER, 3, tx_hash);
+    let pooled = lookup.find(&provider, SENDER, 3, None, 10).await.unwrap().unwrap();
+    assert_eq!(pooled.tx_hash, Some(tx_hash));
+    assert_eq!(pooled.max_fee_per_gas, Some(30_000_000_000));
+    assert_eq!(node.calls("txpool_contentFrom"), 2);
+
+    assert!(lookup.find(&provider, SENDER, 3, None, 11).await.unwrap().is_some());
+    assert_eq!(node.calls("txpool_contentFrom"), 3, "a new block refetches");
+}
+
+#[tokio::test]
+async fn test_mempool_tips_cached_per_block() {
+    let other = address!("000000000000000000000000000000000000dEaD");
+    let node = MockNode::new();
+    node.respond("txpool_content", serde_json::json!({
+        "pending": {
+            SENDER.to_string(): { "0": pooled_tx_json(SENDER, 0, 30_000_000_000, 5_000_000_000) },
+            other.to_string(): {
+                "0": pooled_tx_json(other, 0, 12_000_000_000, 3_000_000_000),
+                "1": pooled_tx_json(other, 1, 9_000_000_000, 1_000_000_000),
+            },
+        },
+        "queued": {},
+    }));
+    let provider = node.provider();
+    let lookup = TxPoolLookup::new(PendingDetection::Auto);
+
+    // the sender's own transactions and those unable to pay the base fee are skipped
+    let tips = lookup.pending_tips(&provider, 10, 10_000_000_000, Some(SENDER)).await.unwrap();
+    assert_eq!(tips, Some(vec![(2_000_000_000, 21_000)]));
+    let tips = lookup.pending_tips(&provider, 10, 8_000_000_000, None).await.unwrap().unwrap();
+    assert_eq!(tips.len(), 3);
+    assert_eq!(node.calls("txpool_content"), 1, "same block is served from the cache");
+
+    lookup.pending_tips(&provider, 11, 8_000_000_000, None).await.unwrap();
+    assert_eq!(node.calls("txpool_content"), 2);
+}
+
+#[tokio::test]
+async fn test_mempool_tips_without_txpool() {
+    let node = MockNode::new();
+    node.reject("txpool_content");
+    let provider = node.provider();
+    let lookup = TxPoolLookup::new(PendingDetection::Auto);
+
+    assert_eq!(lookup.pending_tips(&provider, 10, 10_000_000_000, None).await.unwrap(), None);
+    assert_eq!(lookup.pending_tips(&provider, 11, 10_000_000_000, None).await.unwrap(), None);
+    assert_eq!(node.calls("txpool_content"), 1, "txpool_content is not retried once rejected");
+}
+
+/// A successful 21000 gas transfer of `SENDER` mined at `effective_gas_price`.
+fn receipt_json(tx_hash: B256, effective_gas_price: u128) -> serde_json::Value {
+    serde_json::json!({
+        "type": "0x2",
+        "status": "0x1",
+        "transactionHash": tx_hash,
+        "transactionIndex": "0x0",
+        "blockHash": B256::with_last_byte(0xbb),
+        "blockNumber": "0x2",
+        "from": SENDER,
+        "to": SENDER,
+        "contractAddress": null,
+        "gasUsed": "0x5208",
+        "cumulativeGasUsed": "0x5208",
+        "effectiveGasPrice": format!("{effective_gas_price:#x}"),
+        "logs": [],
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+    })
+}
+
+#[tokio::test]
+async fn test_escalation_handle_records_budget() {
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_sendTransaction", serde_json::json!(B256::with_last_byte(1)));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionReceipt", receipt_json(B256::with_last_byte(1), 1_000_000_000));
+    let provider = node.provider();
+    let budget = GasBudget::new(BudgetAction::Block).with_window(Duration::from_secs(3_600), 1_000_000_000_000_000);
+    // reserved by the filler when the transaction was filled
+    budget.reserve(SENDER, 0, 500_000_000_000_000);
+
+    let tx = TransactionRequest::default().from(SENDER).with_to(SENDER).with_nonce(0);
+    let handle = provider.send_escalating_transaction(tx).await.unwrap().with_budget(budget.clone());
+    let receipt = tokio::time::timeout(Duration::from_secs(10), handle.get_receipt()).await.unwrap().unwrap();
+
+    assert_eq!(receipt.gas_used, 21_000);
+    assert_eq!(budget.remaining(SENDER), 1_000_000_000_000_000 - 21_000_000_000_000);
+}
+
+#[tokio::test]
+async fn test_rebroadcast_waits_for_receipt_after_refusal() {
+    let node = MockNode::new();
+    node.respond("eth_blockNumber", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionCount", serde_json::json!("0x0"));
+    node.respond("eth_estimateGas", serde_json::json!("0x5208"));
+    node.respond("txpool_contentFrom", serde_json::json!({ "pending": {}, "queued": {} }));
+    node.respond("eth_sendTransaction", serde_json::json!(B256::with_last_byte(0)));
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_pending_detection(PendingDetection::TxPool);
+    let provider = ProviderBuilder::new().filler(filler).on_provider(node.provider());
+
+    let tx = TransactionRequest::default()
+        .from(SENDER)
+        .with_to(address!("000000000000000000000000000000000000dEaD"))
+        .with_gas_price(2_000_000_000)
+        .with_nonce(0);
+    let handle = provider.send_escalating_transaction(tx).await.unwrap();
+
+    // past the escalator's valid length, the filler refuses the resend
+    node.respond("txpool_contentFrom", serde_json::json!({
+        "pending": { "0": pooled_tx_json(SENDER, 0, 2_000_000_000, 2_000_000_000) },
+        "queued": {},
+    }));
+    node.respond("eth_blockNumber", serde_json::json!("0x14"));
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(node.calls("eth_estimateGas"), 2);
+    node.respond("eth_blockNumber", serde_json::json!("0x15"));
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(node.calls("eth_estimateGas"), 2, "a refused transaction should not be re-sent");
+
+    // but the version already broadcast is still mined
+    node.respond("eth_getTransactionCount", serde_json::json!("0x1"));
+    node.respond("eth_getTransactionReceipt", receipt_json(B256::with_last_byte(0), 1_000_000_000));
+    let receipt = tokio::time::timeout(Duration::from_secs(10), handle.get_receipt()).await.unwrap().unwrap();
+    assert_eq!(receipt.transaction_hash, B256::with_last_byte(0));
+}
 
 #[tokio::test]  
 async fn test_gas_escalator_filler() {
@@ -32,14 +1006,7 @@ async fn test_underpriced_stuck_in_txpool() {
     let gas_anvil = GasAnvil::new();
     gas_anvil.set_1559_config(2_500_000_000, 1_150_000_000);
 
-    let filler = GasEscalatorFiller::with_escalator(LinearEscalator {
-        start_bid: 1_000_000_000,
-        increment: 100_000_000,
-        max_bid: 10_000_000_000,
-        start_block: 0,
-        valid_length: 10,
-        current_bid: Arc::new(Mutex::new(1_000_000_000)),
-    });
+    let filler = GasEscalatorFiller::with_escalator(escalator());
     let provider = ProviderBuilder::new().filler(filler).on_anvil_with_wallet();
     provider.anvil_set_auto_mine(false).await.unwrap();
     // provider.anvil_set_next_block_base_fee_per_gas(U256::from(1_100_000_000)).await.unwrap();
@@ -96,14 +1063,13 @@ async fn test_escalator_max_bid_not_enough() {
     let gas_anvil = GasAnvil::new();
     gas_anvil.set_1559_config(15_000_000_000, 5_000_000_000);
 
-    let filler = GasEscalatorFiller::with_escalator(LinearEscalator {
-        start_bid: 1_000_000_000,
-        increment: 100_000_000,
-        max_bid: 1_500_000_000,
-        start_block: 0,
-        valid_length: 10,
-        current_bid: Arc::new(Mutex::new(1_000_000_000)),
-    });
+    let filler = GasEscalatorFiller::with_escalator(LinearEscalator::new(
+        1_000_000_000,
+        100_000_000,
+        1_500_000_000,
+        0,
+        10,
+    ));
 
     let provider = ProviderBuilder::new().filler(filler).on_anvil_with_wallet();
     provider.anvil_set_auto_mine(false).await.unwrap();
@@ -127,7 +1093,16 @@ async fn test_escalator_max_bid_not_enough() {
     let mut tx_hash: B256 = B256::ZERO;
 
     while attempts < 10 {
-        let pending_tx = provider.send_transaction(tx.clone()).await.unwrap();
+        let pending_tx = match provider.send_transaction(tx.clone()).await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) => {
+                assert!(
+                    matches!(GasError::from_transport_error(&e), Some(GasError::MaxBidReached { .. })),
+                    "replacement should stop at the max bid: {e}"
+                );
+                break;
+            }
+        };
         tx_hash = *pending_tx.tx_hash();
 
         gas_anvil.mine(&provider, tx_hash, tx.clone()).await.unwrap();
@@ -135,6 +1110,139 @@ async fn test_escalator_max_bid_not_enough() {
         attempts += 1;
     }
 
+    assert!(attempts < 10, "escalation should hit the max bid");
     let receipt = provider.get_transaction_receipt(tx_hash).await.unwrap();
     assert!(receipt.is_none(), "tx should not be mined");
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_send_escalating_transaction() {
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    let provider = ProviderBuilder::new().filler(filler).on_anvil_with_wallet();
+    provider.anvil_set_auto_mine(false).await.unwrap();
+
+    let tx = TransactionRequest::default()
+        .from(provider.default_signer_address())
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(125))
+        .with_max_fee_per_gas(2_000_000_001)
+        .with_max_priority_fee_per_gas(1)
+        .with_nonce(0)
+        .with_chain_id(provider.get_chain_id().await.unwrap());
+
+    let handle = provider.send_escalating_transaction(tx).await.unwrap();
+    provider.anvil_mine(Some(U256::from(1)), None).await.unwrap();
+
+    let receipt = tokio::time::timeout(std::time::Duration::from_secs(10), handle.get_receipt())
+        .await
+        .expect("rebroadcast task should resolve once mined")
+        .unwrap();
+    assert!(receipt.status(), "tx should be mined");
+}
+
+#[tokio::test]
+async fn test_cancel_transaction() {
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    let provider = ProviderBuilder::new().filler(filler).on_anvil_with_wallet();
+    provider.anvil_set_auto_mine(false).await.unwrap();
+
+    let sender = provider.default_signer_address();
+    let tx = TransactionRequest::default()
+        .from(sender)
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(125))
+        .with_max_fee_per_gas(2_000_000_001)
+        .with_max_priority_fee_per_gas(1)
+        .with_nonce(0)
+        .with_chain_id(provider.get_chain_id().await.unwrap());
+    let stuck_hash = *provider.send_transaction(tx).await.unwrap().tx_hash();
+
+    let handle = provider.cancel_transaction(sender, 0).await.unwrap();
+    provider.anvil_mine(Some(U256::from(1)), None).await.unwrap();
+
+    let receipt = tokio::time::timeout(std::time::Duration::from_secs(10), handle.get_receipt())
+        .await
+        .expect("rebroadcast task should resolve once mined")
+        .unwrap();
+    assert_eq!(receipt.to, Some(sender), "cancellation should be a self-transfer");
+    assert_eq!(receipt.gas_used, 21_000);
+    assert!(provider.get_transaction_receipt(stuck_hash).await.unwrap().is_none());
+    assert_eq!(provider.get_transaction_count(sender).await.unwrap(), 1, "nonce should be consumed");
+}
+
+#[tokio::test]
+async fn test_nonce_count_detection() {
+    let filler = GasEscalatorFiller::with_escalator(escalator()).with_pending_detection(PendingDetection::NonceCount);
+    let provider = ProviderBuilder::new().filler(filler.clone()).on_anvil_with_wallet();
+    provider.anvil_set_auto_mine(false).await.unwrap();
+
+    let sender = provider.default_signer_address();
+    let tx = TransactionRequest::default()
+        .from(sender)
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(125))
+        .with_max_fee_per_gas(2_000_000_001)
+        .with_max_priority_fee_per_gas(1)
+        .with_nonce(0)
+        .with_chain_id(provider.get_chain_id().await.unwrap());
+
+    let tx_hash1 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    filler.record_tx_hash(sender, 0, tx_hash1);
+
+    // the replacement is only accepted if the pending transaction was detected and escalated
+    let tx_hash2 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    assert_ne!(tx_hash1, tx_hash2);
+
+    let state = filler.state(sender, 0).unwrap();
+    assert_eq!(state.attempts, 2);
+    assert_eq!(state.tx_hash, Some(tx_hash1));
+}
+
+#[tokio::test]
+async fn test_queued_transaction_not_escalated() {
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    let provider = ProviderBuilder::new().filler(filler.clone()).on_anvil_with_wallet();
+    provider.anvil_set_auto_mine(false).await.unwrap();
+
+    let sender = provider.default_signer_address();
+    // nonce 0 is never sent, so nonce 1 sits in the queued sub-pool
+    let tx = TransactionRequest::default()
+        .from(sender)
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(125))
+        .with_max_fee_per_gas(2_000_000_001)
+        .with_max_priority_fee_per_gas(1)
+        .with_nonce(1)
+        .with_chain_id(provider.get_chain_id().await.unwrap());
+
+    let tx_hash1 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    let tx_hash2 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    assert_ne!(tx_hash1, tx_hash2);
+
+    let state = filler.state(sender, 1).unwrap();
+    assert_eq!(state.attempts, 1, "queued transaction should not advance the escalation");
+}
+
+#[tokio::test]
+async fn test_legacy_gas_price_escalation() {
+    let filler = GasEscalatorFiller::with_escalator(escalator());
+    let provider = ProviderBuilder::new().filler(filler.clone()).on_anvil_with_wallet();
+    provider.anvil_set_auto_mine(false).await.unwrap();
+
+    let sender = provider.default_signer_address();
+    let tx = TransactionRequest::default()
+        .from(sender)
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(125))
+        .with_gas_price(1_000_000_000)
+        .with_nonce(0)
+        .with_chain_id(provider.get_chain_id().await.unwrap());
+
+    let tx_hash1 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    let tx_hash2 = *provider.send_transaction(tx.clone()).await.unwrap().tx_hash();
+    assert_ne!(tx_hash1, tx_hash2);
+
+    let replacement = provider.get_transaction_by_hash(tx_hash2).await.unwrap().unwrap();
+    assert_eq!(replacement.gas_price(), Some(2_000_000_000), "gas price should include the escalated bid");
+    assert_eq!(filler.state(sender, 0).unwrap().attempts, 2);
+}
@@ -1,238 +1,221 @@
-use std::cmp::Ordering;
-use std::sync::{Arc, Mutex};
-use std::{fs::File, io::BufReader};
-use std::collections::{BinaryHeap, HashMap, VecDeque};
-
-use alloy_primitives::{address, utils, Address, U256};
-use alloy_provider::fillers::{GasFiller, TxFiller};
-use alloy_provider::{Provider, ProviderBuilder, SendableTx, WalletProvider};
-use alloy_network::TransactionBuilder;
-use alloy_rpc_types::{BlockNumberOrTag, TransactionRequest};
-use serde::Deserialize;
-use serde_json::Result;
-
-use crate::{GasEscalatorFiller, LinearEscalator};
-
-#[derive(Debug, Copy, Clone, Deserialize)]
-struct BlockFeeData {
-    block_number: u64,
-    base_fee_per_gas: u128,
-}
+use std::collections::BTreeMap;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
-struct TransactionData {
-    block_number: u64,
-    transaction_index: u64,
-    from_address: Address,
-    gas_limit: u64,
-    gas_used: u64,
-    gas_price: u128,
-}
+use alloy_primitives::{address, Address};
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
-enum TxStrategy {
-    Naive,
-    Escalator,
-}
+use crate::simulation::{
+    Bid, BlockDataset, BlockFeeData, EscalatorStrategy, InclusionModel, NaiveStrategy, Percentiles,
+    SimulationReport, Simulator, TransactionData,
+};
+use crate::LinearEscalator;
 
-#[allow(dead_code)]
-#[derive(Debug)]
-struct PendingTransaction {
-    tx_request: TransactionRequest,
-    block_number: u64,
-    strategy: TxStrategy,
-}
+const SENDER: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
 
-impl PendingTransaction {
-    fn priority_fee(&self) -> u128 {
-        self.tx_request.max_priority_fee_per_gas.unwrap_or(0)
-    }
+fn block(block_number: u64, base_fee_per_gas: u128) -> BlockFeeData {
+    BlockFeeData { block_number, base_fee_per_gas, gas_used: 15_000_000 }
 }
 
-impl PartialEq for PendingTransaction {
-    fn eq(&self, other: &Self) -> bool {
-        self.priority_fee() == other.priority_fee()
+fn transaction(block_number: u64, transaction_index: u64, gas_price: u128) -> TransactionData {
+    TransactionData {
+        block_number,
+        transaction_index,
+        from_address: SENDER,
+        gas_limit: 21_000,
+        gas_used: 21_000,
+        gas_price,
     }
 }
 
-impl PartialOrd for PendingTransaction {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.priority_fee().cmp(&other.priority_fee()))
-    }
+/// Five blocks at a 10 gwei base fee whose cheapest transaction tips 1, 1, 3, 2.5 and 2 gwei.
+fn dataset() -> BlockDataset {
+    let blocks = (100..105).map(|block_number| block(block_number, 10_000_000_000)).collect();
+    let transactions = vec![
+        transaction(100, 0, 11_000_000_000),
+        transaction(101, 0, 11_000_000_000),
+        transaction(101, 1, 15_000_000_000),
+        transaction(102, 0, 13_000_000_000),
+        transaction(103, 0, 13_000_000_000),
+        transaction(103, 1, 12_500_000_000),
+        transaction(104, 0, 13_000_000_000),
+        transaction(104, 1, 12_000_000_000),
+    ];
+    BlockDataset::new(blocks, transactions)
 }
 
-impl Eq for PendingTransaction {}
-
-impl Ord for PendingTransaction {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.priority_fee().cmp(&other.priority_fee())
-    }
+#[test]
+fn test_naive_strategy_never_escalates() {
+    let simulator = Simulator::new(dataset()).with_range(102, 104);
+    let result = simulator.run(&NaiveStrategy::new(20.0));
+
+    assert_eq!(result.transactions.len(), 3);
+    // priced at 1 gwei from block 101, below the cheapest transaction of every later block
+    assert_eq!(result.transactions[0].included_at, None, "an underpriced naive bid should stay pending");
+    assert_eq!(result.transactions[0].bid.unwrap().max_priority_fee_per_gas, 1_000_000_000);
+    assert_eq!(result.transactions[1].included_at, Some(103));
+    assert_eq!(result.transactions[2].included_at, Some(104));
+    assert!(result.transactions.iter().all(|tx| !tx.expired));
 }
 
-fn load_transaction_data(path: &str) -> Vec<TransactionData> {
-    let file = File::open(path).expect("Failed to open transaction data file");
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).expect("Failed to parse transaction data")
+#[test]
+fn test_escalator_strategy_escalates_until_included() {
+    let escalator = LinearEscalator::new(1_000_000_000, 1_000_000_000, 10_000_000_000, 0, 2);
+    let simulator = Simulator::new(dataset()).with_range(102, 104).with_submit_every(2);
+    let result = simulator.run(&EscalatorStrategy::new("linear", escalator));
+
+    assert_eq!(result.strategy, "linear");
+    let first = result.transactions[0];
+    assert_eq!(first.submitted_at, 102);
+    assert!(first.expired, "1 and 2 gwei bids should not land before the escalator expires");
+
+    let simulator = Simulator::new(dataset()).with_range(102, 104);
+    let escalator = LinearEscalator::new(1_000_000_000, 1_000_000_000, 10_000_000_000, 0, 5);
+    let result = simulator.run(&EscalatorStrategy::new("linear", escalator));
+
+    let first = result.transactions[0];
+    assert_eq!(first.included_at, Some(104), "the 3 gwei bid should displace the 2 gwei transaction");
+    assert_eq!(first.latency(), Some(2));
+    assert_eq!(first.effective_tip, Some(3_000_000_000));
+    assert_eq!(first.fee_paid, 13_000_000_000 * 21_000);
+    assert_eq!(result.latencies(), vec![2]);
+    assert_eq!(result.total_fees(), 13_000_000_000 * 21_000);
 }
 
-fn load_block_fee_data(path: &str) -> Vec<BlockFeeData> {
-    let file = File::open(path).expect("Failed to open block fee data file");
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).expect("Failed to parse block fee data")
+#[test]
+fn test_missing_block_skips_a_bid() {
+    let mut blocks: Vec<_> = (100..105).map(|block_number| block(block_number, 10_000_000_000)).collect();
+    blocks.retain(|block| block.block_number != 102);
+    let transactions = vec![transaction(101, 0, 11_000_000_000), transaction(103, 0, 12_500_000_000), transaction(104, 0, 12_000_000_000)];
+    let simulator = Simulator::new(BlockDataset::new(blocks, transactions)).with_range(102, 104);
+
+    let escalator = LinearEscalator::new(1_000_000_000, 1_000_000_000, 10_000_000_000, 0, 5);
+    let result = simulator.run(&EscalatorStrategy::new("linear", escalator));
+    let first = result.transactions[0];
+    assert!(!first.expired, "a block without fee data should not expire the transaction");
+    assert_eq!(first.included_at, Some(104));
+    assert_eq!(first.effective_tip, Some(3_000_000_000));
+
+    let result = simulator.run(&NaiveStrategy::new(20.0));
+    let second = result.transactions[1];
+    assert_eq!(second.submitted_at, 103);
+    assert!(!second.expired && second.bid.is_none(), "a naive bid without fee data should wait");
 }
 
-#[tokio::test]
-async fn simulate_gas_escalator() {
-    // Load block data from JSON file
-    // let raw_transactions = load_transaction_data("data/transactions.json");
-    // let block_fee_data = load_block_fee_data("data/blocks.json");
-    
-    let raw_transactions = load_transaction_data("data/cleaned_transactions_1000.json");
-    let block_fee_data = load_block_fee_data("data/blocks_1000.json");
-
-    let mut blocks:HashMap<u64, (u128, Vec<TransactionData>)> = HashMap::new();
-    for &block in &block_fee_data {
-        blocks.entry(block.block_number).or_insert((block.base_fee_per_gas, Vec::new()));
-    }
-
-
-    for tx in raw_transactions {
-        if let Some((base_fee, transactions)) = blocks.get_mut(&tx.block_number) {
-            if tx.gas_price >= *base_fee {
-                transactions.push(tx);
-            }
-        }
-        continue;
-        println!("block_number: {:?} {:?} {:?} {:?} {:?} {:?}", tx.block_number, tx.from_address, tx.transaction_index, tx.gas_limit, tx.gas_price, tx.gas_used);
-    }
-
-    let mut priority_fee_data: HashMap<u64, u128> = HashMap::new();
-    let mut sorted_block_numbers: Vec<u64> = blocks.keys().cloned().collect();
-    sorted_block_numbers.sort();
-
-    for &block_number in &sorted_block_numbers {
-        let block_info = &blocks.get(&block_number).unwrap();
-        let mut tips: Vec<u128> = block_info.1.iter().filter_map(|tx| {
-            if tx.gas_price >= block_info.0 {
-                Some(tx.gas_price - block_info.0)
-            } else {
-                None
-            }
-        }).collect();
-
-        if tips.is_empty() {
-            tips.push(1_000_000_000u128); // Fallback if no valid tips are found
-        }
-
-        tips.sort_unstable();
-        let index = usize::min(20 * tips.len() / 100, tips.len() - 1);
-        let tip = tips[index];
-        priority_fee_data.insert(block_number, tip);
-    }
-    println!("priority_fee_data: {:?}", priority_fee_data);
-
-    let start_bid = 1_000_000_000u128;
-    let increment = 1_000_000_000u128;
-    let max_bid = 10_000_000_000u128;
-
-    let simple_filler = GasFiller::default();
-    let simple_provider = ProviderBuilder::new().filler(simple_filler).on_anvil_with_wallet();
-
-    let escalator_filler = GasEscalatorFiller::with_escalator(LinearEscalator {
-        start_bid: 5_000_000_000,
-        increment: 5_000_000_000,
-        max_bid: 50_000_000_000,
-        start_block: 12349000,
-        valid_length: 120,
-        current_bid: Arc::new(Mutex::new(5_000_000_000)),
-    });
-
-
-    let mut block_numbers: Vec<u64> = blocks.keys().cloned().collect();
-    block_numbers.sort();
-
-    let simulation_start_block = block_numbers[1];
-    let simulation_end_block = simulation_start_block + 999;
-    let mut pending_transactions_naive = BinaryHeap::new();
-    // let mut pending_transactions_escalator = BinaryHeap::new();
-    let mut results_naive = Vec::new();
-    // let mut results_escalator = Vec::new();
-
-    for current_block in simulation_start_block..simulation_end_block {
-        let (_, block_txs) = &blocks.get(&current_block).unwrap();
-        if block_txs.len() == 0 {
-            continue;
-        }
-
-        
-        // let fee_history = simple_provider
-        // .get_fee_history(
-        //     10,
-        //     BlockNumberOrTag::Latest,
-        //     &[20.0],
-        // )
-        // .await.unwrap();
-
-        
-        let previous_block = current_block - 1;
-        
-        let max_priority_fee_per_gas = *priority_fee_data.get(&previous_block).unwrap();
-        let max_fee_per_gas = 2 * &blocks.get(&previous_block).unwrap().0 + max_priority_fee_per_gas;
-        println!("max_fee_per_gas: {:?} {:?}", max_fee_per_gas, max_priority_fee_per_gas);
-
-        let tx_request_naive = create_test_tx(&simple_provider, max_fee_per_gas, max_priority_fee_per_gas).await;
-
-        pending_transactions_naive.push(PendingTransaction {
-            tx_request: tx_request_naive,
-            block_number: current_block,
-            strategy: TxStrategy::Naive,
-        });
-
-        while let Some(pending_tx) = pending_transactions_naive.pop() {
-            if let Some((base_fee, txs)) = blocks.get(&current_block) {
-                if would_be_included((base_fee, txs), pending_tx.tx_request.max_fee_per_gas.unwrap_or(0), pending_tx.priority_fee() 
-            ) {
-                    println!("would be included in block {:?}", current_block);
-                    results_naive.push((current_block, pending_tx.priority_fee()));
-                    continue;
-                } else {
-                    println!("not included in block {:?}", current_block);
-                    pending_transactions_naive.push(pending_tx);
-                    break;
-                }
-            } 
-        }
-    }
-
-    fn would_be_included(block: (&u128, &Vec<TransactionData>), base_fee: u128, priority_fee: u128) -> bool {
-        let (block_fee, included_txs) = block;
-        let mut sorted_txs = included_txs.clone();
-        sorted_txs.sort_by(|a, b| b.gas_price.cmp(&a.gas_price));
-        if *block_fee > base_fee {
-            return false;
-        }
-        
-        for tx in sorted_txs {
-            if priority_fee >= (tx.gas_price - block_fee) {
-                return true;
-            }
-        }
-        false
-    }
-
-        
+#[test]
+fn test_strategies_compared_side_by_side() {
+    let simulator = Simulator::new(dataset())
+        .with_range(102, 104)
+        .with_strategy(NaiveStrategy::new(20.0))
+        .with_strategy(EscalatorStrategy::new(
+            "linear",
+            LinearEscalator::new(1_000_000_000, 1_000_000_000, 10_000_000_000, 0, 5),
+        ));
+
+    let results = simulator.run_all();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0], simulator.run(&NaiveStrategy::new(20.0)), "side by side replay should match a single run");
+
+    let naive = &results[0];
+    assert_eq!(naive.strategy, "naive-p20");
+    assert_eq!(naive.included().count(), 2);
+    assert_eq!(naive.mean_latency(), Some(0.0));
+
+    let linear = &results[1];
+    assert_eq!(linear.strategy, "linear");
+    assert_eq!(linear.included().count(), 1);
+    assert!(linear.transactions.iter().all(|tx| !tx.expired));
+    assert_eq!(linear.mean_latency(), Some(2.0));
+    assert_eq!(linear.total_fees(), 13_000_000_000 * 21_000);
+}
 
+#[test]
+fn test_simulation_report() {
+    let simulator = Simulator::new(dataset()).with_range(102, 104);
+    let report = simulator.report(&simulator.run(&NaiveStrategy::new(20.0)));
+
+    assert_eq!((report.submitted, report.included, report.expired, report.pending), (3, 2, 0, 1));
+    assert!((report.inclusion_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+    assert_eq!(report.latency, Some(Percentiles { p50: 0, p90: 0, p99: 0, max: 0 }));
+    assert_eq!(report.latency_histogram, BTreeMap::from([(0, 2)]));
+    assert_eq!(report.mean_tip, Some(2_750_000_000));
+    assert_eq!(
+        report.tip,
+        Some(Percentiles { p50: 3_000_000_000, p90: 3_000_000_000, p99: 3_000_000_000, max: 3_000_000_000 })
+    );
+    assert_eq!(report.total_fees, (13_000_000_000 + 12_500_000_000) * 21_000);
+    // both bids paid 0.5 gwei above the cheapest transaction they had to outbid, plus 1 wei
+    assert_eq!(report.overpayment, 2 * (500_000_000 - 1) * 21_000);
+    assert_eq!(simulator.clone().with_strategy(NaiveStrategy::new(20.0)).compare(), vec![report.clone()]);
+
+    let mut json = Vec::new();
+    SimulationReport::write_json(std::slice::from_ref(&report), &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[0]["strategy"], "naive-p20");
+    assert_eq!(json[0]["latency_histogram"]["0"], 2);
+
+    let mut csv = Vec::new();
+    SimulationReport::write_csv(&[report], &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+    assert!(lines[1].starts_with("naive-p20,3,2,0,1,"));
+    assert!(lines[1].ends_with(&format!(",{},{}", 25_500_000_000u128 * 21_000, 2 * 499_999_999u128 * 21_000)));
 }
 
-async fn create_test_tx<P: Provider + WalletProvider>(provider: &P, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> TransactionRequest {
-    TransactionRequest::default()
-        .with_from(provider.default_signer_address())
-        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
-        .with_value(U256::from(125))
-        .with_max_fee_per_gas(max_fee_per_gas)
-        .with_max_priority_fee_per_gas(max_priority_fee_per_gas)
-        .with_nonce(0)
-        .with_chain_id(provider.get_chain_id().await.unwrap())
+#[test]
+fn test_inclusion_model_packs_blocks_by_tip() {
+    let dataset = dataset();
+    let full = dataset.block(101).unwrap();
+    let bid = |tip: u128| Bid { max_fee_per_gas: 20_000_000_000, max_priority_fee_per_gas: tip };
+    let model = InclusionModel::default();
+
+    assert!(model.includes(full, &bid(2_000_000_000), 21_000), "2 gwei should displace the 1 gwei transaction");
+    assert!(!model.includes(full, &bid(1_000_000_000), 21_000), "ties should go to the historical transaction");
+    assert!(!model.includes(full, &bid(2_000_000_000), 42_000), "only 21000 gas pays less than 2 gwei");
+    assert!(model.includes(full, &bid(6_000_000_000), 42_000));
+    assert!(
+        !model.includes(full, &Bid { max_fee_per_gas: 9_000_000_000, max_priority_fee_per_gas: 6_000_000_000 }, 21_000),
+        "a bid below the base fee can never be included"
+    );
+
+    let spare_room = model.with_gas_limit(15_100_000);
+    assert!(spare_room.includes(full, &bid(0), 42_000), "unused room should be open to any bid paying the base fee");
+    assert!(!spare_room.includes(full, &bid(0), 200_000));
+
+    let private_flow = model.with_private_flow(0.5);
+    assert!(
+        !private_flow.includes(full, &bid(2_000_000_000), 21_000),
+        "the 1 gwei transaction came first, as private order flow"
+    );
+    assert!(private_flow.includes(full, &bid(6_000_000_000), 21_000));
+
+    let empty = BlockDataset::new(vec![block(200, 10_000_000_000)], Vec::new());
+    assert!(!model.with_gas_limit(30_000_000).includes(empty.block(200).unwrap(), &bid(6_000_000_000), 21_000));
 }
 
+#[test]
+fn simulate_gas_escalator() {
+    let dataset = BlockDataset::load("data/blocks.json", "data/transactions.json")
+        .expect("Failed to load block data");
+    let (first_block, last_block) = dataset.range().unwrap();
+    let simulator = Simulator::new(dataset)
+        .with_range(first_block + 1, last_block)
+        .with_strategy(NaiveStrategy::new(20.0))
+        .with_strategy(EscalatorStrategy::new(
+            "linear",
+            LinearEscalator::new(5_000_000_000, 5_000_000_000, 50_000_000_000, 0, 120),
+        ));
+
+    let results = simulator.run_all();
+    let naive = &results[0];
+    assert_eq!(naive.transactions.len(), 2, "every submitted transaction should be reported");
+    assert_eq!(naive.latencies(), vec![0, 0], "the p20 tip clears both replayed blocks");
+    assert_eq!(naive.transactions[0].effective_tip, Some(160_000_000));
+    assert_eq!(naive.transactions[1].effective_tip, Some(311_619_955));
+    assert_eq!(naive.total_fees(), 472_059_724_563_000 + 475_164_236_526_000);
+
+    let linear = &results[1];
+    assert_eq!(linear.latencies(), vec![0, 0]);
+    assert!(linear.transactions.iter().all(|tx| tx.effective_tip == Some(5_000_000_000)));
+    assert_eq!(linear.total_fees(), 573_699_724_563_000 + 573_620_217_471_000);
+    assert!(linear.total_fees() > naive.total_fees(), "a 5 gwei start bid overpays a quiet market");
+}
@@ -0,0 +1,370 @@
+use std::{collections::{BTreeMap, HashMap}, future::IntoFuture, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}};
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types::TransactionTrait;
+use alloy_network::{Network, TransactionResponse};
+use alloy_provider::{ext::TxPoolApi, Provider};
+use alloy_transport::{RpcError, Transport, TransportResult};
+
+use crate::GasError;
+
+/// How [`GasEscalatorFiller`](crate::GasEscalatorFiller) detects that a transaction with the
+/// same nonce is already waiting in the mempool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PendingDetection {
+    /// Use the `txpool_` namespace and fall back to [`PendingDetection::NonceCount`] once the
+    /// node rejects it.
+    #[default]
+    Auto,
+    /// Only use the `txpool_` namespace.
+    TxPool,
+    /// Compare the sender's `pending` and `latest` transaction counts, which every standard
+    /// JSON-RPC node supports.
+    NonceCount,
+}
+
+/// The txpool sub-pool a transaction is waiting in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubPool {
+    /// Executable, but not yet included; usually underpriced.
+    Pending,
+    /// Not executable yet because an earlier nonce of the sender is missing.
+    Queued,
+}
+
+/// A transaction already in the mempool for the `(from, nonce)` being filled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PooledTransaction {
+    pub tx_hash: Option<B256>,  // Unknown when detected by nonce count without a remembered hash
+    pub pool: SubPool,
+    pub max_fee_per_gas: Option<u128>,          // Gas price for legacy transactions, unknown without a hash
+    pub max_priority_fee_per_gas: Option<u128>, // Gas price for legacy transactions, unknown without a hash
+}
+
+impl PooledTransaction {
+    fn unpriced(pool: SubPool) -> Self {
+        Self { tx_hash: None, pool, max_fee_per_gas: None, max_priority_fee_per_gas: None }
+    }
+}
+
+/// Pooled transactions of a single sender, by nonce.
+type PoolSnapshot = HashMap<u64, PooledTransaction>;
+
+/// Fees of an executable transaction in the mempool.
+#[derive(Clone, Copy, Debug)]
+struct PoolTip {
+    sender: Address,
+    max_fee_per_gas: u128,          // Gas price for legacy transactions
+    max_priority_fee_per_gas: u128, // Gas price for legacy transactions
+    gas_limit: u64,
+}
+
+/// Executable transactions of the whole mempool.
+type PoolTips = Vec<PoolTip>;
+
+impl PoolTip {
+    /// Returns the tip paid at base fee `base_fee`, or `None` if it cannot pay `base_fee`.
+    fn effective_tip(&self, base_fee: u128) -> Option<u128> {
+        let max_tip = self.max_fee_per_gas.checked_sub(base_fee)?;
+        Some(std::cmp::min(max_tip, self.max_priority_fee_per_gas))
+    }
+}
+
+/// Pool of a single sender as of a block.
+#[derive(Clone, Debug, Default)]
+struct CachedPool {
+    block: u64,
+    snapshot: PoolSnapshot,
+    unsent: HashMap<u64, PooledTransaction>, // Broadcasts prepared since the snapshot, not yet confirmed sent
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TxPoolLookup {
+    detection: PendingDetection,
+    txpool_unsupported: Arc<AtomicBool>,
+    content_from_unsupported: Arc<AtomicBool>,
+    cache: Arc<Mutex<HashMap<Address, CachedPool>>>,
+    tips: Arc<Mutex<Option<(u64, PoolTips)>>>, // Executable transactions and the block they were fetched at
+}
+
+impl TxPoolLookup {
+    pub(crate) fn new(detection: PendingDetection) -> Self {
+        Self { detection, ..Default::default() }
+    }
+
+    /// Looks up the transaction sent by `from` with `nonce` as of `current_block`. `known_hash`
+    /// is the hash remembered for it locally, used when the pool cannot be inspected.
+    pub(crate) async fn find<P, T, N>(
+        &self,
+        provider: &P,
+        from: Address,
+        nonce: u64,
+        known_hash: Option<B256>,
+        current_block: u64,
+    ) -> TransportResult<Option<PooledTransaction>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let use_txpool = match self.detection {
+            PendingDetection::Auto => !self.txpool_unsupported.load(Ordering::Relaxed),
+            PendingDetection::TxPool => true,
+            PendingDetection::NonceCount => false,
+        };
+
+        if use_txpool {
+            match self.find_in_txpool(provider, from, nonce, current_block).await {
+                Err(RpcError::ErrorResp(e)) if self.detection == PendingDetection::Auto => {
+                    println!("txpool_ namespace unavailable ({}), falling back to nonce count", e);
+                    self.txpool_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(RpcError::ErrorResp(e)) => {
+                    println!("txpool_ namespace unavailable ({})", e);
+                    return Err(GasError::TxPoolUnsupported.into());
+                }
+                result => return result,
+            }
+        }
+
+        self.find_by_nonce_count(provider, from, nonce, known_hash).await
+    }
+
+    /// Returns the effective tip and gas limit of every executable transaction in the mempool
+    /// as of `current_block`, like [`pending_tips`], or `None` if the node does not expose the
+    /// `txpool_` namespace. The mempool is fetched once per block.
+    pub(crate) async fn pending_tips<P, T, N>(
+        &self,
+        provider: &P,
+        current_block: u64,
+        base_fee: u128,
+        exclude: Option<Address>,
+    ) -> TransportResult<Option<Vec<(u128, u64)>>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        if self.txpool_unsupported.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        if let Some((block, pool)) = &*self.tips.lock().unwrap() {
+            if *block == current_block {
+                return Ok(Some(tips_at(pool, base_fee, exclude)));
+            }
+        }
+
+        let pool = match fetch_pool_tips(provider).await {
+            Ok(pool) => pool,
+            Err(RpcError::ErrorResp(e)) => {
+                println!("txpool_ namespace unavailable ({}), not pricing from the mempool", e);
+                self.txpool_unsupported.store(true, Ordering::Relaxed);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let tips = tips_at(&pool, base_fee, exclude);
+        *self.tips.lock().unwrap() = Some((current_block, pool));
+
+        Ok(Some(tips))
+    }
+
+    /// Records that `(from, nonce)` is about to be broadcast with the given fees. `mined_nonce`
+    /// is the sender's `latest` transaction count.
+    ///
+    /// The node may still reject the broadcast, so until [`TxPoolLookup::confirm_sent`] lookups
+    /// of `(from, nonce)` skip the cache.
+    pub(crate) fn note_sent(
+        &self,
+        from: Address,
+        nonce: u64,
+        mined_nonce: u64,
+        current_block: u64,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    ) {
+        let mut cache = self.cache.lock().unwrap();
+        let Some(cached) = cache.get_mut(&from) else {
+            return;
+        };
+        if cached.block != current_block {
+            return;
+        }
+
+        let previous_pending = nonce
+            .checked_sub(1)
+            .and_then(|previous| cached.snapshot.get(&previous))
+            .is_some_and(|previous| previous.pool == SubPool::Pending);
+        let pool = if nonce == mined_nonce || previous_pending { SubPool::Pending } else { SubPool::Queued };
+        // the broadcast replaces whatever was pooled for this nonce
+        cached.unsent.insert(nonce, PooledTransaction {
+            tx_hash: None,
+            pool,
+            max_fee_per_gas: Some(max_fee_per_gas),
+            max_priority_fee_per_gas: Some(max_priority_fee_per_gas),
+        });
+    }
+
+    /// Records that the broadcast of `(from, nonce)` noted by [`TxPoolLookup::note_sent`] was
+    /// accepted as `tx_hash`, so lookups during the rest of its block are served from the cache.
+    pub(crate) fn confirm_sent(&self, from: Address, nonce: u64, tx_hash: B256) {
+        let mut cache = self.cache.lock().unwrap();
+        let Some(cached) = cache.get_mut(&from) else {
+            return;
+        };
+        if let Some(mut sent) = cached.unsent.remove(&nonce) {
+            sent.tx_hash = Some(tx_hash);
+            cached.snapshot.insert(nonce, sent);
+        }
+    }
+
+    async fn find_in_txpool<P, T, N>(
+        &self,
+        provider: &P,
+        from: Address,
+        nonce: u64,
+        current_block: u64,
+    ) -> TransportResult<Option<PooledTransaction>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        if let Some(cached) = self.cache.lock().unwrap().get(&from) {
+            if cached.block == current_block && !cached.unsent.contains_key(&nonce) {
+                return Ok(cached.snapshot.get(&nonce).cloned());
+            }
+        }
+
+        let snapshot = self.fetch_snapshot(provider, from).await?;
+        let found = snapshot.get(&nonce).cloned();
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.block >= current_block);
+        cache.insert(from, CachedPool { block: current_block, snapshot, unsent: HashMap::new() });
+
+        Ok(found)
+    }
+
+    /// Fetches the pooled transactions of `from`, using `txpool_contentFrom` when the node
+    /// supports it and the whole `txpool_content` otherwise.
+    async fn fetch_snapshot<P, T, N>(&self, provider: &P, from: Address) -> TransportResult<PoolSnapshot>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        if !self.content_from_unsupported.load(Ordering::Relaxed) {
+            match provider.txpool_content_from(from).await {
+                Ok(content) => return Ok(snapshot(content.pending, content.queued)),
+                Err(RpcError::ErrorResp(e)) => {
+                    println!("txpool_contentFrom unavailable ({}), falling back to txpool_content", e);
+                    self.content_from_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut content = provider.txpool_content().await?;
+        Ok(snapshot(
+            content.pending.remove(&from).unwrap_or_default(),
+            content.queued.remove(&from).unwrap_or_default(),
+        ))
+    }
+
+    async fn find_by_nonce_count<P, T, N>(
+        &self,
+        provider: &P,
+        from: Address,
+        nonce: u64,
+        known_hash: Option<B256>,
+    ) -> TransportResult<Option<PooledTransaction>>
+    where
+        P: Provider<T, N>,
+        T: Transport + Clone,
+        N: Network,
+    {
+        let (pending_count, latest_count) = futures::try_join!(
+            provider.get_transaction_count(from).pending().into_future(),
+            provider.get_transaction_count(from).latest().into_future(),
+        )?;
+
+        if nonce < latest_count {
+            return Ok(None);
+        }
+
+        let pool = if nonce < pending_count { SubPool::Pending } else { SubPool::Queued };
+        let pooled = match known_hash {
+            Some(tx_hash) => provider.get_transaction_by_hash(tx_hash).await?.map(|tx| pooled_transaction(tx, pool)),
+            None => None,
+        };
+
+        // without its hash, a nonce beyond the pending count cannot be seen
+        Ok(pooled.or_else(|| (pool == SubPool::Pending).then(|| PooledTransaction::unpriced(pool))))
+    }
+}
+
+/// Returns the effective tip and gas limit of every executable transaction in the mempool,
+/// at base fee `base_fee`, except those of `exclude`. Transactions that cannot pay `base_fee`
+/// are skipped.
+pub(crate) async fn pending_tips<P, T, N>(
+    provider: &P,
+    base_fee: u128,
+    exclude: Option<Address>,
+) -> TransportResult<Vec<(u128, u64)>>
+where
+    P: Provider<T, N>,
+    T: Transport + Clone,
+    N: Network,
+{
+    Ok(tips_at(&fetch_pool_tips(provider).await?, base_fee, exclude))
+}
+
+/// Fetches the fees of every executable transaction in the mempool.
+async fn fetch_pool_tips<P, T, N>(provider: &P) -> TransportResult<PoolTips>
+where
+    P: Provider<T, N>,
+    T: Transport + Clone,
+    N: Network,
+{
+    let content = provider.txpool_content().await?;
+
+    Ok(content
+        .pending
+        .into_iter()
+        .flat_map(|(sender, txs)| txs.into_values().map(move |tx| (sender, tx)))
+        .map(|(sender, tx)| {
+            let max_fee_per_gas = TransactionTrait::max_fee_per_gas(&tx);
+            PoolTip {
+                sender,
+                max_fee_per_gas,
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas().unwrap_or(max_fee_per_gas),
+                gas_limit: tx.gas_limit(),
+            }
+        })
+        .collect())
+}
+
+fn tips_at(pool: &[PoolTip], base_fee: u128, exclude: Option<Address>) -> Vec<(u128, u64)> {
+    pool.iter()
+        .filter(|tx| Some(tx.sender) != exclude)
+        .filter_map(|tx| Some((tx.effective_tip(base_fee)?, tx.gas_limit)))
+        .collect()
+}
+
+fn snapshot<T: TransactionResponse>(pending: BTreeMap<String, T>, queued: BTreeMap<String, T>) -> PoolSnapshot {
+    let pending = pending.into_values().map(|tx| (SubPool::Pending, tx));
+    let queued = queued.into_values().map(|tx| (SubPool::Queued, tx));
+
+    pending.chain(queued).map(|(pool, tx)| (tx.nonce(), pooled_transaction(tx, pool))).collect()
+}
+
+fn pooled_transaction<T: TransactionResponse>(tx: T, pool: SubPool) -> PooledTransaction {
+    let max_fee_per_gas = TransactionTrait::max_fee_per_gas(&tx);
+    PooledTransaction {
+        tx_hash: Some(tx.tx_hash()),
+        pool,
+        max_fee_per_gas: Some(max_fee_per_gas),
+        max_priority_fee_per_gas: Some(tx.max_priority_fee_per_gas().unwrap_or(max_fee_per_gas)),
+    }
+}
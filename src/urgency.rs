@@ -0,0 +1,61 @@
+/// How quickly a transaction needs to be included, mapped to a complete escalation preset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Urgency {
+    Slow,
+    #[default]
+    Standard,
+    Fast,
+    Instant,
+}
+
+/// Escalation parameters of an [`Urgency`] level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UrgencyPreset {
+    pub percentile: f64,             // Fee history reward percentile of the first bid
+    pub bump_percent: u128,          // Increase per block, in percent of the first bid
+    pub max_base_fee_multiple: u128, // Max bid as a multiple of the next block's base fee
+    pub valid_length: u64,           // Duration in blocks
+}
+
+impl Urgency {
+    pub fn preset(self) -> UrgencyPreset {
+        match self {
+            Urgency::Slow => UrgencyPreset {
+                percentile: 10.0,
+                bump_percent: 5,
+                max_base_fee_multiple: 1,
+                valid_length: 50,
+            },
+            Urgency::Standard => UrgencyPreset {
+                percentile: 25.0,
+                bump_percent: 10,
+                max_base_fee_multiple: 2,
+                valid_length: 25,
+            },
+            Urgency::Fast => UrgencyPreset {
+                percentile: 50.0,
+                bump_percent: 20,
+                max_base_fee_multiple: 3,
+                valid_length: 10,
+            },
+            Urgency::Instant => UrgencyPreset {
+                percentile: 75.0,
+                bump_percent: 50,
+                max_base_fee_multiple: 5,
+                valid_length: 3,
+            },
+        }
+    }
+}
+
+impl UrgencyPreset {
+    /// Returns the max bid of the preset when the next block's base fee is `base_fee`.
+    pub fn max_bid(&self, base_fee: u128) -> u128 {
+        base_fee.saturating_mul(self.max_base_fee_multiple)
+    }
+
+    /// Returns the increase per block of the preset when the first bid is `start_bid`.
+    pub fn increment(&self, start_bid: u128) -> u128 {
+        start_bid.saturating_mul(self.bump_percent) / 100
+    }
+}